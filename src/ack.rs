@@ -0,0 +1,234 @@
+//! Acknowledgement tracking and escalation for transmissions that require a human response
+
+use crate::{AppState, TransmissionRequest};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Acknowledgement state carried by a transmission and each of its escalated replays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AckSettings {
+    /// ID of the original transmission - shared by every escalation
+    pub id: String,
+    /// 0 for the original transmission, incremented for each escalation
+    pub attempt: u32,
+    pub timeout_secs: u64,
+    pub max_escalations: u32,
+}
+
+impl AckSettings {
+    /// Settings for a new transmission, with per-request overrides falling back to the config
+    pub fn new(id: &str, timeout_secs: Option<u64>, max_escalations: Option<u32>) -> Self {
+        let config = crate::config::get();
+        AckSettings {
+            id: id.to_string(),
+            attempt: 0,
+            timeout_secs: timeout_secs.unwrap_or(config.acks.timeout_secs),
+            max_escalations: max_escalations.unwrap_or(config.acks.max_escalations),
+        }
+    }
+}
+
+/// An unacknowledged transmission
+#[derive(Clone, Serialize)]
+pub struct PendingAck {
+    pub id: String,
+    pub text: String,
+    pub attempts: u32,
+    pub first_queued_at: chrono::DateTime<chrono::Utc>,
+    pub client: Option<String>,
+}
+
+/// Outstanding acknowledgements, keyed by original transmission ID
+#[derive(Default)]
+pub struct AckTracker {
+    pending: Mutex<HashMap<String, PendingAck>>,
+}
+
+impl AckTracker {
+    /// URL a human can open (GET) or POST to acknowledge a transmission
+    pub fn ack_url(&self, id: &str) -> String {
+        // Ack links must be reachable by the listener, so default to the bind address
+        let config = crate::config::get();
+        let base_url = config.acks.base_url.clone().unwrap_or_else(|| {
+            format!(
+                "http://{}",
+                config.server.bind_address.replace("0.0.0.0", "127.0.0.1")
+            )
+        });
+        format!("{}/ack/{}", base_url.trim_end_matches('/'), id)
+    }
+
+    /// Start waiting for an acknowledgement (no-op if already tracked)
+    pub fn register(&self, req: &TransmissionRequest) {
+        let Some(ack) = &req.ack else {
+            return;
+        };
+
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(ack.id.clone())
+            .or_insert_with(|| PendingAck {
+                id: ack.id.clone(),
+                text: req.text.clone(),
+                attempts: ack.attempt,
+                first_queued_at: req.queued_at,
+                client: req.client.clone(),
+            });
+    }
+
+    pub fn is_pending(&self, id: &str) -> bool {
+        self.pending.lock().unwrap().contains_key(id)
+    }
+
+    /// Mark a transmission as acknowledged, returning false if it wasn't outstanding
+    pub fn acknowledge(&self, id: &str) -> bool {
+        self.pending.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Number of transmissions still waiting for acknowledgement
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    fn list(&self) -> Vec<PendingAck> {
+        let mut pending: Vec<PendingAck> = self.pending.lock().unwrap().values().cloned().collect();
+        pending.sort_by_key(|p| p.first_queued_at);
+        pending
+    }
+
+    fn record_attempt(&self, id: &str, attempt: u32) {
+        if let Some(pending) = self.pending.lock().unwrap().get_mut(id) {
+            pending.attempts = attempt;
+        }
+    }
+}
+
+/// Build the louder, more urgent replay of an unacknowledged transmission
+fn escalate(req: &TransmissionRequest, ack: &AckSettings) -> TransmissionRequest {
+    // Only prefix once - later escalations are built from an already-prefixed text
+    let text = if ack.attempt == 0 {
+        format!("Unacknowledged alert. {}", req.text)
+    } else {
+        req.text.clone()
+    };
+
+    TransmissionRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        queued_at: chrono::Utc::now(),
+        text,
+        // Louder up to the maximum gain, but never quieter than the original
+        volume: (req.volume * 1.25)
+            .min(*crate::VOLUME_RANGE.end())
+            .max(req.volume),
+        tone_type: req.tone_type.escalate(),
+        enable_toast: true,
+        toast_urgency: req.toast_urgency.escalate(),
+        ack: Some(AckSettings {
+            attempt: ack.attempt + 1,
+            ..ack.clone()
+        }),
+        ..req.clone()
+    }
+}
+
+/// Called once a transmission has played: wait for the acknowledgement, then escalate
+/// (re-enqueue louder) or, after the last escalation, fire the escalation hook
+pub fn await_ack(state: Arc<AppState>, req: TransmissionRequest) {
+    let Some(ack) = req.ack.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(ack.timeout_secs)).await;
+
+        if !state.acks.is_pending(&ack.id) {
+            return;
+        }
+
+        if ack.attempt < ack.max_escalations {
+            let escalated = escalate(&req, &ack);
+            info!(
+                id = %ack.id,
+                attempt = ack.attempt + 1,
+                max_escalations = ack.max_escalations,
+                "Transmission not acknowledged after {}s - escalating",
+                ack.timeout_secs
+            );
+            state.acks.record_attempt(&ack.id, ack.attempt + 1);
+            if let Err(e) = state.enqueue(escalated) {
+                error!("Failed to enqueue escalation: {}", e);
+            }
+            return;
+        }
+
+        // Out of escalations - stop replaying and hand off to the escalation hook
+        let Some(pending) = state.acks.pending.lock().unwrap().remove(&ack.id) else {
+            return;
+        };
+        warn!(
+            id = %ack.id,
+            "Transmission never acknowledged after {} escalation(s)",
+            ack.max_escalations
+        );
+        fire_escalation_hook(&pending).await;
+    });
+}
+
+#[derive(Serialize)]
+struct EscalationEvent<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    ack: &'a PendingAck,
+}
+
+/// POST the unacknowledged transmission to the configured escalation webhook, if any
+async fn fire_escalation_hook(pending: &PendingAck) {
+    let Some(url) = crate::config::get().acks.escalation_webhook.clone() else {
+        return;
+    };
+
+    let event = EscalationEvent {
+        event: "ack_escalation",
+        ack: pending,
+    };
+
+    match reqwest::Client::new().post(url).json(&event).send().await {
+        Ok(response) if response.status().is_success() => {
+            info!(id = %pending.id, "Escalation hook notified");
+        }
+        Ok(response) => {
+            warn!("Escalation hook returned {}", response.status());
+        }
+        Err(e) => error!("Failed to call escalation hook: {}", e),
+    }
+}
+
+/// GET or POST /ack/{id} - acknowledge a transmission
+pub async fn ack_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if !state.acks.acknowledge(&id) {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Error: No pending acknowledgement for {}", id),
+        );
+    }
+
+    info!(id = %id, "Transmission acknowledged");
+    (StatusCode::OK, "Transmission acknowledged".to_string())
+}
+
+/// GET /acks - list transmissions still waiting for acknowledgement
+pub async fn list_acks_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.acks.list())
+}
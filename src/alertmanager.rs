@@ -0,0 +1,275 @@
+//! Prometheus Alertmanager webhook receiver
+
+use crate::config::AlertmanagerConfig;
+use crate::{AppState, PlayRequest, ToastUrgency};
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tracing::{error, info};
+
+/// Alertmanager webhook payload (version 4)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    #[serde(default)]
+    pub receiver: String,
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+#[derive(Deserialize)]
+pub struct Alert {
+    pub status: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+}
+
+/// Map an alert's `severity` label onto a toast urgency
+fn urgency(alert: &Alert) -> ToastUrgency {
+    match alert.labels.get("severity").map(|s| s.to_lowercase()) {
+        Some(severity) if matches!(severity.as_str(), "page" | "high" | "fatal") => {
+            ToastUrgency::Critical
+        }
+        Some(severity) => ToastUrgency::from_str(&severity),
+        None => ToastUrgency::Warning,
+    }
+}
+
+/// Fill `{{status}}`, `{{labels.NAME}}`, `{{annotations.NAME}}` and the shorthands
+/// `{{alertname}}`, `{{severity}}`, `{{instance}}`, `{{summary}}` and `{{description}}`.
+/// Placeholders the alert has no value for are dropped.
+fn render(template: &str, alert: &Alert) -> String {
+    let lookup = |name: &str| {
+        let value = match name {
+            "status" => Some(&alert.status),
+            "alertname" | "severity" | "instance" => alert.labels.get(name),
+            "summary" | "description" => alert.annotations.get(name),
+            _ => match name.split_once('.') {
+                Some(("labels", label)) => alert.labels.get(label),
+                Some(("annotations", annotation)) => alert.annotations.get(annotation),
+                _ => None,
+            },
+        };
+        Ok(value.map(|value| Value::String(value.clone())))
+    };
+    let text = match crate::templates::fill(template, lookup) {
+        Ok((text, _)) => text,
+        Err(e) => {
+            error!("Failed to render Alertmanager template: {}", e);
+            template.to_string()
+        }
+    };
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    text.trim_end_matches(['.', ' ']).to_string()
+}
+
+fn request(text: String, urgency: ToastUrgency, tone: Option<&str>) -> PlayRequest {
+    PlayRequest {
+        text,
+        tone: tone.map(|t| t.to_string()),
+        enable_toast: Some(true),
+        toast_urgency: Some(urgency.as_str().to_string()),
+        ..Default::default()
+    }
+}
+
+/// Build one transmission per alert, folding any beyond `max_alerts` into a summary
+fn transmissions(payload: &WebhookPayload, config: &AlertmanagerConfig) -> Vec<PlayRequest> {
+    let alerts: Vec<&Alert> = payload
+        .alerts
+        .iter()
+        .filter(|alert| alert.status == "firing" || config.send_resolved)
+        .collect();
+
+    let mut requests: Vec<PlayRequest> = alerts
+        .iter()
+        .take(config.max_alerts)
+        .map(|alert| {
+            if alert.status == "resolved" {
+                let text = render(&config.resolved_template, alert);
+                request(text, ToastUrgency::Info, Some("THREE-NOTE-CHIME"))
+            } else {
+                let text = render(&config.firing_template, alert);
+                request(text, urgency(alert), None)
+            }
+        })
+        .collect();
+
+    let remaining = &alerts[alerts.len().min(config.max_alerts)..];
+    if !remaining.is_empty() {
+        let firing = remaining.iter().filter(|a| a.status == "firing").count();
+        let text = format!(
+            "{} more alert(s): {} firing, {} resolved",
+            remaining.len(),
+            firing,
+            remaining.len() - firing
+        );
+        let urgencies: Vec<ToastUrgency> = remaining
+            .iter()
+            .filter(|a| a.status == "firing")
+            .map(|a| urgency(a))
+            .collect();
+        let urgency = if urgencies.contains(&ToastUrgency::Critical) {
+            ToastUrgency::Critical
+        } else if urgencies.contains(&ToastUrgency::Warning) {
+            ToastUrgency::Warning
+        } else {
+            ToastUrgency::Info
+        };
+        requests.push(request(text, urgency, None));
+    }
+
+    requests
+}
+
+/// Whether the request carries "Authorization: Bearer <token>"
+pub fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|provided| secrets_match(provided, token))
+}
+
+/// Compare a provided secret with the expected one in constant time, so response timing
+/// doesn't reveal how much of it was right
+pub fn secrets_match(provided: &str, expected: &str) -> bool {
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+/// POST /hooks/alertmanager - speak firing and resolved alerts
+pub async fn alertmanager_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<WebhookPayload>,
+) -> impl IntoResponse {
+    // Read per request so config changes apply without a restart
+    let config = crate::config::get().alertmanager.clone();
+    if config
+        .token
+        .as_ref()
+        .is_some_and(|token| !authorized(&headers, token))
+    {
+        return (
+            StatusCode::UNAUTHORIZED,
+            "Error: Invalid or missing bearer token".to_string(),
+        );
+    }
+
+    info!(
+        "Alertmanager webhook from receiver '{}' with {} alert(s)",
+        payload.receiver,
+        payload.alerts.len()
+    );
+
+    let mut queued = 0;
+    for request in transmissions(&payload, &config) {
+        let transmission = request.into_transmission().and_then(|mut transmission| {
+            transmission.client = Some(format!("alertmanager:{}", payload.receiver));
+            state.enqueue(transmission)
+        });
+        match transmission {
+            Ok(_) => queued += 1,
+            Err(e) => error!("Failed to enqueue alert: {}", e),
+        }
+    }
+
+    (StatusCode::OK, format!("Queued {} transmission(s)", queued))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(labels: &[(&str, &str)], annotations: &[(&str, &str)]) -> Alert {
+        let map = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        Alert {
+            status: "firing".to_string(),
+            labels: map(labels),
+            annotations: map(annotations),
+        }
+    }
+
+    #[test]
+    fn render_fills_shorthands_labels_and_annotations() {
+        let alert = alert(
+            &[
+                ("alertname", "DiskFull"),
+                ("severity", "critical"),
+                ("team", "infra"),
+            ],
+            &[("summary", "Disk is 95% full"), ("runbook", "see wiki")],
+        );
+        assert_eq!(
+            render(
+                "{{status}}: {{severity}} {{alertname}} for {{labels.team}}. {{summary}}. {{annotations.runbook}}.",
+                &alert
+            ),
+            "firing: critical DiskFull for infra. Disk is 95% full. see wiki"
+        );
+    }
+
+    #[test]
+    fn render_drops_missing_placeholders() {
+        let alert = alert(&[("alertname", "DiskFull")], &[]);
+        assert_eq!(
+            render("{{severity}} alert: {{alertname}}. {{summary}}", &alert),
+            "alert: DiskFull"
+        );
+        assert_eq!(
+            render("{{alertname}} {{summary | default:no summary}}", &alert),
+            "DiskFull no summary"
+        );
+    }
+
+    #[test]
+    fn render_keeps_placeholders_inside_values() {
+        let alert = alert(
+            &[
+                ("alertname", "{{summary}}"),
+                ("instance", "{{labels.secret}}"),
+            ],
+            &[("summary", "Disk full")],
+        );
+        assert_eq!(
+            render("{{alertname}} on {{instance}}: {{summary}}", &alert),
+            "{{summary}} on {{labels.secret}}: Disk full"
+        );
+    }
+
+    #[test]
+    fn urgency_follows_severity() {
+        for (severity, expected) in [
+            (Some("critical"), ToastUrgency::Critical),
+            (Some("PAGE"), ToastUrgency::Critical),
+            (Some("high"), ToastUrgency::Critical),
+            (Some("fatal"), ToastUrgency::Critical),
+            (Some("warning"), ToastUrgency::Warning),
+            (Some("info"), ToastUrgency::Info),
+            (None, ToastUrgency::Warning),
+        ] {
+            let labels: Vec<(&str, &str)> = severity.map(|s| ("severity", s)).into_iter().collect();
+            assert_eq!(
+                urgency(&alert(&labels, &[])).as_str(),
+                expected.as_str(),
+                "severity {:?}",
+                severity
+            );
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Slack/Discord-compatible incoming webhook shim, so existing chat integrations can
+//! be pointed at the service unchanged
+
+use crate::{AppState, PlayRequest};
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock};
+use tracing::info;
+
+/// Per-integration overrides passed as query parameters on the webhook URL
+#[derive(Deserialize)]
+pub struct ChatOptions {
+    pub profile: Option<String>,
+    pub voice: Option<String>,
+    pub tone: Option<String>,
+    pub urgency: Option<String>,
+    pub toast: Option<bool>,
+    pub token: Option<String>,
+}
+
+fn str_at<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.trim().is_empty())
+}
+
+fn array_at<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Text of a Block Kit text object or a plain string
+fn block_text(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| str_at(value, "text"))
+}
+
+/// Collect the readable text of Slack Block Kit blocks
+fn slack_blocks(blocks: &[Value], parts: &mut Vec<String>) {
+    for block in blocks {
+        if let Some(text) = block.get("text").and_then(block_text) {
+            parts.push(text.to_string());
+        }
+        for field in array_at(block, "fields") {
+            if let Some(text) = block_text(field) {
+                parts.push(text.to_string());
+            }
+        }
+        // context blocks and rich text sections nest their text in elements
+        for element in array_at(block, "elements") {
+            if let Some(text) = str_at(element, "text") {
+                parts.push(text.to_string());
+            }
+            slack_blocks(array_at(element, "elements"), parts);
+        }
+    }
+}
+
+/// Flatten a Slack or Discord payload into message parts, plus an urgency hint
+fn extract(payload: &Value) -> (Vec<String>, Option<&'static str>) {
+    let mut parts = Vec::new();
+    let mut urgency = None;
+
+    // Discord
+    if let Some(content) = str_at(payload, "content") {
+        parts.push(content.to_string());
+    }
+    for embed in array_at(payload, "embeds") {
+        parts.extend(
+            ["title", "description"]
+                .iter()
+                .filter_map(|key| str_at(embed, key))
+                .map(String::from),
+        );
+        for field in array_at(embed, "fields") {
+            if let (Some(name), Some(value)) = (str_at(field, "name"), str_at(field, "value")) {
+                parts.push(format!("{}: {}", name, value));
+            }
+        }
+    }
+
+    // Slack - when blocks are present, `text` is only the notification fallback
+    let blocks = array_at(payload, "blocks");
+    if blocks.is_empty() {
+        if let Some(text) = str_at(payload, "text") {
+            parts.push(text.to_string());
+        }
+    } else {
+        slack_blocks(blocks, &mut parts);
+    }
+    for attachment in array_at(payload, "attachments") {
+        let before = parts.len();
+        parts.extend(
+            ["pretext", "title", "text"]
+                .iter()
+                .filter_map(|key| str_at(attachment, key))
+                .map(String::from),
+        );
+        for field in array_at(attachment, "fields") {
+            if let (Some(title), Some(value)) = (str_at(field, "title"), str_at(field, "value")) {
+                parts.push(format!("{}: {}", title, value));
+            }
+        }
+        slack_blocks(array_at(attachment, "blocks"), &mut parts);
+        if parts.len() == before {
+            parts.extend(str_at(attachment, "fallback").map(String::from));
+        }
+
+        match str_at(attachment, "color") {
+            Some("danger") => urgency = Some("critical"),
+            Some("warning") if urgency.is_none() => urgency = Some("warning"),
+            _ => {}
+        }
+    }
+
+    (parts, urgency)
+}
+
+/// Slack `<url|label>`, `<@U123|name>`, `<#C123|channel>`, `<!subteam^ID|@team>`
+static LABELLED_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[^<>|\s]+\|@?([^<>]+)>").unwrap());
+static SPECIAL_MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!(here|channel|everyone)>").unwrap());
+/// Unlabelled mentions `<@U123>`, `<#C123>`, `<@&role>` and Discord custom emoji
+/// `<:name:id>` / `<a:name:id>`. Bare `<https://...>` links are left to speech
+/// normalization.
+static ANGLE_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(?:[@#!:]|a:)[^<>\s]*>").unwrap());
+/// `:white_check_mark:`, `:+1:`, `:skin-tone-2:` - with at least one letter (or the
+/// thumbs) and not straight after a word or colon, so times like 10:30:00 are left alone
+static EMOJI_SHORTCODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(^|[^\w:]):(?:[a-zA-Z0-9_+\-']*[a-zA-Z][a-zA-Z0-9_+\-']*|[+-]1):").unwrap()
+});
+/// Slack's single-tilde strikethrough
+static STRIKE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"~([^~\n]+)~").unwrap());
+
+/// Strip the markup only Slack and Discord use - mentions, labelled links, emoji and
+/// HTML entities. Markdown, URLs and line breaks are left to speech normalization.
+fn speakable(text: &str) -> String {
+    let text = LABELLED_LINK.replace_all(text, "$1");
+    let text = SPECIAL_MENTION.replace_all(&text, "$1");
+    let text = ANGLE_TOKEN.replace_all(&text, " ");
+    // Adjacent shortcodes (":x::fire:") share no boundary, so repeat until none are left
+    let mut text = text.into_owned();
+    while EMOJI_SHORTCODE.is_match(&text) {
+        text = EMOJI_SHORTCODE.replace_all(&text, "$1 ").into_owned();
+    }
+    let text = STRIKE.replace_all(&text, "$1");
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+/// Parse a JSON body, or Slack's legacy `payload=<json>` form encoding
+fn parse_body(headers: &HeaderMap, body: &[u8]) -> Result<Value, String> {
+    let is_form = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+
+    // Like Slack, accept raw JSON even when it's sent with a form content type (curl -d)
+    let form_payload = is_form
+        .then(|| serde_urlencoded::from_bytes::<Vec<(String, String)>>(body).ok())
+        .flatten()
+        .and_then(|form| form.into_iter().find(|(key, _)| key == "payload"));
+
+    match form_payload {
+        Some((_, payload)) => serde_json::from_str(&payload),
+        None => serde_json::from_slice(body),
+    }
+    .map_err(|e| format!("Invalid JSON payload: {}", e))
+}
+
+/// Convert a chat webhook into a transmission, returning its ID
+fn enqueue_chat(
+    state: &AppState,
+    source: &str,
+    options: ChatOptions,
+    headers: &HeaderMap,
+    addr: &SocketAddr,
+    body: &[u8],
+) -> Result<String, (StatusCode, String)> {
+    let token = crate::config::get().chat.token.clone();
+    if token.is_some_and(|token| options.token.as_deref() != Some(token.as_str())) {
+        return Err((StatusCode::UNAUTHORIZED, "invalid_token".to_string()));
+    }
+
+    let payload = parse_body(headers, body).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let (parts, color_urgency) = extract(&payload);
+    let text = speakable(&parts.join("\n"));
+    if text.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "no_text".to_string()));
+    }
+
+    // Prefix the sender name when the integration sets one
+    let username = str_at(&payload, "username").map(speakable);
+    let text = match username.filter(|name| !name.is_empty()) {
+        Some(name) => format!("{}: {}", name, text),
+        None => text,
+    };
+
+    let urgency = options.urgency.or_else(|| color_urgency.map(String::from));
+    let request = PlayRequest {
+        text,
+        profile: options.profile,
+        voice: options.voice,
+        tone: options.tone,
+        enable_toast: options.toast.or(urgency.as_ref().map(|_| true)),
+        toast_urgency: urgency,
+        ..Default::default()
+    };
+
+    info!(
+        "{} webhook received, adding to queue: {}",
+        source, request.text
+    );
+    let mut transmission = request
+        .into_transmission()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    transmission.client = Some(format!("{} ({})", source.to_lowercase(), addr.ip()));
+    state
+        .enqueue(transmission)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// POST /hooks/slack - Slack incoming webhook compatible (`text`, `blocks`, `attachments`)
+pub async fn slack_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(options): Query<ChatOptions>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    // Slack replies with plain-text "ok" or an error code, and some clients check for it
+    match enqueue_chat(&state, "Slack", options, &headers, &addr, &body) {
+        Ok(_) => (StatusCode::OK, "ok".to_string()),
+        Err(error) => error,
+    }
+}
+
+/// POST /hooks/discord - Discord webhook compatible (`content`, `embeds`)
+pub async fn discord_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(options): Query<ChatOptions>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    // Discord answers 204 No Content unless ?wait=true is passed
+    match enqueue_chat(&state, "Discord", options, &headers, &addr, &body) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err((status, message)) => (
+            status,
+            axum::Json(serde_json::json!({ "message": message, "code": 0 })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_shortcodes_are_removed() {
+        assert_eq!(speakable(":white_check_mark: Deployed :+1:"), "Deployed");
+        assert_eq!(speakable("Done :x::fire::skin-tone-2:"), "Done");
+    }
+
+    #[test]
+    fn times_and_ratios_are_kept() {
+        assert_eq!(
+            speakable("Build failed at 10:30:00"),
+            "Build failed at 10:30:00"
+        );
+        assert_eq!(
+            speakable("ratio 3:2:1, port :8080:"),
+            "ratio 3:2:1, port :8080:"
+        );
+        assert_eq!(speakable("key:value:pair"), "key:value:pair");
+    }
+
+    #[test]
+    fn mentions_and_links() {
+        assert_eq!(
+            speakable("<!here> <@U1|alice> see <https://x.io/a|the run> <:party:123> <@U2>"),
+            "here alice see the run"
+        );
+    }
+}
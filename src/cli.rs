@@ -0,0 +1,443 @@
+//! Command-line interface: `serve` runs the API server, the other subcommands are
+//! thin clients for a running instance so scripts don't need hand-written curl JSON
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::{Map, Value, json};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "quindar", version, about = "Quindar Break-In Communicator")]
+pub struct Cli {
+    /// URL of the running service (default: $QUINDAR_URL, else derived from the bind address)
+    #[arg(long, global = true)]
+    pub url: Option<String>,
+
+    /// Configuration file (default: $QUINDAR_CONFIG, else quindar.toml if present)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration - config file plus environment overrides - and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Print raw JSON responses instead of formatted output
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Same as the mcp subcommand, for MCP client configs that pass `--mcp`
+    #[arg(long, hide = true)]
+    pub mcp: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the API server (the default when no subcommand is given)
+    Serve,
+    /// Queue a spoken transmission and print its ID
+    Say(Box<SayArgs>),
+    /// Show the transmission playing now and those waiting
+    Queue,
+    /// Cancel a queued or playing transmission
+    Cancel { id: String },
+    /// List voices for the configured TTS provider
+    Voices {
+        /// Locale prefix filter, e.g. en or en-GB
+        #[arg(long)]
+        locale: Option<String>,
+    },
+    /// Run the MCP server over stdio, forwarding to the running service
+    Mcp,
+    /// Make the running service re-read its config file and .env
+    Reload,
+}
+
+#[derive(Args)]
+pub struct SayArgs {
+    /// Message to speak (read from stdin if omitted or "-", unless --template is given)
+    pub text: Option<String>,
+    /// Named message template from the server config, used instead of text
+    #[arg(long)]
+    pub template: Option<String>,
+    /// Template variable as NAME=VALUE (repeatable)
+    #[arg(long = "var", value_name = "NAME=VALUE")]
+    pub vars: Vec<String>,
+    /// Named profile from the server config; other options override it
+    #[arg(long)]
+    pub profile: Option<String>,
+    #[arg(long)]
+    pub voice: Option<String>,
+    /// QUINDAR, THREE-NOTE-CHIME or NO-TONE
+    #[arg(long)]
+    pub tone: Option<String>,
+    /// Toast urgency: info, warning or critical (implies --toast)
+    #[arg(long)]
+    pub urgency: Option<String>,
+    #[arg(long)]
+    pub speed: Option<f32>,
+    #[arg(long)]
+    pub volume: Option<f32>,
+    /// Voice pitch shift in Hz, -100 to 100
+    #[arg(long, allow_hyphen_values = true)]
+    pub pitch: Option<i32>,
+    /// Voice loudness change in percent, -100 to 100
+    #[arg(long, allow_hyphen_values = true)]
+    pub voice_volume: Option<i32>,
+    /// Delivery instructions (OpenAI voices only)
+    #[arg(long)]
+    pub instructions: Option<String>,
+    /// Show a desktop notification
+    #[arg(long)]
+    pub toast: bool,
+    /// The message (or rendered template) is SSML
+    #[arg(long)]
+    pub ssml: bool,
+    /// Repeat with escalation until acknowledged
+    #[arg(long)]
+    pub ack: bool,
+}
+
+/// Base URL of the running service
+fn service_url(url: Option<String>, config_path: Option<PathBuf>) -> String {
+    let url = url
+        .or_else(|| std::env::var("QUINDAR_URL").ok())
+        .unwrap_or_else(|| {
+            // The server's own config (file plus BIND_ADDRESS) says where it listens
+            let bind_address = crate::config::load(config_path.as_deref())
+                .map(|loaded| loaded.config.server.bind_address)
+                .unwrap_or_else(|_| {
+                    std::env::var("BIND_ADDRESS").unwrap_or_else(|_| "127.0.0.1:42069".to_string())
+                });
+            format!("http://{}", bind_address.replace("0.0.0.0", "127.0.0.1"))
+        });
+    url.trim_end_matches('/').to_string()
+}
+
+/// The token for admin routes, from the server's own config (file plus ADMIN_TOKEN)
+fn admin_token(config_path: Option<&Path>) -> Option<String> {
+    crate::config::load(config_path)
+        .map(|loaded| loaded.config.server.admin_token)
+        .unwrap_or_else(|_| std::env::var("ADMIN_TOKEN").ok())
+}
+
+/// Run a client subcommand, exiting non-zero on failure
+pub async fn run(
+    command: Command,
+    url: Option<String>,
+    config_path: Option<PathBuf>,
+    raw_json: bool,
+) {
+    // Client commands pick up QUINDAR_URL/BIND_ADDRESS from .env quietly - stdout is for results
+    let _ = dotenv::dotenv();
+    let admin_token = admin_token(config_path.as_deref());
+    let base_url = service_url(url, config_path);
+
+    if let Err(e) = execute(command, &base_url, admin_token, raw_json).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn execute(
+    command: Command,
+    base_url: &str,
+    admin_token: Option<String>,
+    raw_json: bool,
+) -> Result<(), String> {
+    match command {
+        Command::Serve => unreachable!("serve is handled by main"),
+        Command::Mcp => {
+            crate::mcp::run_stdio(base_url.to_string()).await;
+            Ok(())
+        }
+        Command::Say(args) => {
+            let SayArgs {
+                text,
+                template,
+                vars,
+                profile,
+                voice,
+                tone,
+                urgency,
+                speed,
+                volume,
+                pitch,
+                voice_volume,
+                instructions,
+                toast,
+                ssml,
+                ack,
+            } = *args;
+            let mut body = Map::new();
+            match template {
+                Some(_) if text.is_some() => {
+                    return Err("Give either text or --template, not both".to_string());
+                }
+                Some(template) => {
+                    let mut values = Map::new();
+                    for var in vars {
+                        let (name, value) = var
+                            .split_once('=')
+                            .ok_or_else(|| format!("--var '{}' must be NAME=VALUE", var))?;
+                        values.insert(name.trim().to_string(), json!(value));
+                    }
+                    body.insert("template".to_string(), json!(template));
+                    body.insert("vars".to_string(), Value::Object(values));
+                }
+                None if !vars.is_empty() => return Err("--var needs --template".to_string()),
+                None => {
+                    body.insert("text".to_string(), json!(read_text(text)?));
+                }
+            }
+            if let Some(profile) = profile {
+                body.insert("profile".to_string(), json!(profile));
+            }
+            if let Some(voice) = voice {
+                body.insert("voice".to_string(), json!(voice));
+            }
+            if let Some(tone) = tone {
+                body.insert("tone".to_string(), json!(tone));
+            }
+            if let Some(speed) = speed {
+                body.insert("speed".to_string(), json!(speed));
+            }
+            if let Some(volume) = volume {
+                body.insert("volume".to_string(), json!(volume));
+            }
+            if let Some(pitch) = pitch {
+                body.insert("pitch".to_string(), json!(pitch));
+            }
+            if let Some(voice_volume) = voice_volume {
+                body.insert("voice_volume".to_string(), json!(voice_volume));
+            }
+            if let Some(instructions) = instructions {
+                body.insert("instructions".to_string(), json!(instructions));
+            }
+            if ssml {
+                body.insert("ssml".to_string(), json!(true));
+            }
+            if toast || urgency.is_some() {
+                body.insert("enable_toast".to_string(), json!(true));
+            }
+            if let Some(urgency) = urgency {
+                body.insert("toast_urgency".to_string(), json!(urgency));
+            }
+            if ack {
+                body.insert("require_ack".to_string(), json!(true));
+            }
+            say(base_url, Value::Object(body), raw_json).await
+        }
+        Command::Queue => queue(base_url, raw_json).await,
+        Command::Cancel { id } => cancel(base_url, &id).await,
+        Command::Voices { locale } => voices(base_url, locale.as_deref(), raw_json).await,
+        Command::Reload => reload(base_url, admin_token, raw_json).await,
+    }
+}
+
+fn read_text(text: Option<String>) -> Result<String, String> {
+    let text = match text.as_deref() {
+        None | Some("-") => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| format!("Failed to read text from stdin: {}", e))?;
+            input
+        }
+        Some(text) => text.to_string(),
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("No text to say".to_string());
+    }
+    Ok(text.to_string())
+}
+
+async fn send(
+    request: reqwest::RequestBuilder,
+    base_url: &str,
+) -> Result<reqwest::Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Quindar service at {}: {}", base_url, e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(body.trim_start_matches("Error: ").to_string());
+    }
+    Ok(response)
+}
+
+async fn get_json(base_url: &str, path: &str) -> Result<Value, String> {
+    let request = reqwest::Client::new().get(format!("{}{}", base_url, path));
+    send(request, base_url)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from Quindar service: {}", e))
+}
+
+async fn say(base_url: &str, body: Value, raw_json: bool) -> Result<(), String> {
+    let request = reqwest::Client::new()
+        .post(format!("{}/play", base_url))
+        .header("X-Client-Name", "quindar-cli")
+        .json(&body);
+    let response = send(request, base_url).await?;
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let id = header("X-Transmission-Id");
+    let ack_url = header("X-Ack-Url");
+    let body = response.text().await.unwrap_or_default();
+    let Some(id) = id else {
+        return Err(body.trim_start_matches("Error: ").to_string());
+    };
+
+    if raw_json {
+        println!("{}", json!({ "id": id, "ack_url": ack_url }));
+    } else {
+        println!("{}", id);
+        if let Some(ack_url) = ack_url {
+            eprintln!("Acknowledge: {}", ack_url);
+        }
+    }
+    Ok(())
+}
+
+async fn queue(base_url: &str, raw_json: bool) -> Result<(), String> {
+    let status = get_json(base_url, "/queue").await?;
+    if raw_json {
+        println!("{}", status);
+        return Ok(());
+    }
+
+    let format_entry = |entry: &Value| {
+        format!(
+            "{}  {:<16}  {}",
+            entry["id"].as_str().unwrap_or_default(),
+            entry["tone"].as_str().unwrap_or_default(),
+            entry["text"].as_str().unwrap_or_default()
+        )
+    };
+
+    match status.get("current").filter(|c| !c.is_null()) {
+        Some(current) => println!("Playing:\n  {}", format_entry(current)),
+        None => println!("Playing: nothing"),
+    }
+
+    let pending = status["pending"].as_array().cloned().unwrap_or_default();
+    if pending.is_empty() {
+        println!("Pending: none");
+    } else {
+        println!("Pending ({}):", pending.len());
+        for entry in &pending {
+            println!("  {}", format_entry(entry));
+        }
+    }
+    Ok(())
+}
+
+async fn cancel(base_url: &str, id: &str) -> Result<(), String> {
+    let request = reqwest::Client::new().delete(format!("{}/queue/{}", base_url, id));
+    send(request, base_url).await?;
+    println!("Transmission {} cancelled", id);
+    Ok(())
+}
+
+async fn voices(base_url: &str, locale: Option<&str>, raw_json: bool) -> Result<(), String> {
+    let path = match locale {
+        Some(locale) => format!(
+            "/voices?{}",
+            serde_urlencoded::to_string([("locale", locale)]).map_err(|e| e.to_string())?
+        ),
+        None => "/voices".to_string(),
+    };
+    let list = get_json(base_url, &path).await?;
+    if raw_json {
+        println!("{}", list);
+        return Ok(());
+    }
+
+    println!(
+        "Provider: {} (default voice: {})",
+        list["provider"].as_str().unwrap_or_default(),
+        list["default_voice"].as_str().unwrap_or_default()
+    );
+    for voice in list["voices"].as_array().cloned().unwrap_or_default() {
+        let details: Vec<&str> = ["locale", "gender", "description"]
+            .iter()
+            .filter_map(|field| voice[field].as_str())
+            .collect();
+        println!(
+            "  {:<32} {}",
+            voice["name"].as_str().unwrap_or_default(),
+            details.join("  ")
+        );
+    }
+    Ok(())
+}
+
+async fn reload(base_url: &str, admin_token: Option<String>, raw_json: bool) -> Result<(), String> {
+    let mut request = reqwest::Client::new().post(format!("{}/admin/reload", base_url));
+    if let Some(token) = admin_token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Quindar service at {}: {}", base_url, e))?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(
+            "The service requires its admin token - set ADMIN_TOKEN or server.admin_token"
+                .to_string(),
+        );
+    }
+
+    // A rejected config comes back as 422 with the problems in the JSON body
+    let report: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from Quindar service: {}", e))?;
+    if raw_json {
+        println!("{}", report);
+    }
+
+    let list = |field: &str| -> Vec<String> {
+        report[field]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect()
+    };
+    if report["reloaded"].as_bool() != Some(true) {
+        return Err(format!(
+            "Configuration rejected - keeping the current settings:\n  {}",
+            list("errors").join("\n  ")
+        ));
+    }
+    if raw_json {
+        return Ok(());
+    }
+
+    let changed = list("changed");
+    if changed.is_empty() {
+        println!("Configuration reloaded - no changes");
+    } else {
+        println!("Configuration reloaded: {}", changed.join(", "));
+    }
+    let restart_required = list("restart_required");
+    if !restart_required.is_empty() {
+        println!("Restart required to apply: {}", restart_required.join(", "));
+    }
+    Ok(())
+}
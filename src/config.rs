@@ -0,0 +1,910 @@
+//! Typed service configuration: an optional TOML file, overridden by environment variables
+//! (including those from .env), validated as a whole at startup
+
+use crate::logging::{LogFormat, LogRotation};
+use crate::{ToastUrgency, ToneType, TtsProvider};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Looked for next to the executable, then in the current directory, when no file is named
+pub const DEFAULT_CONFIG_FILE: &str = "quindar.toml";
+
+/// Shown in place of secrets by --print-config
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tts: TtsConfig,
+    pub defaults: DefaultsConfig,
+    pub acks: AckConfig,
+    pub alertmanager: AlertmanagerConfig,
+    pub chat: ChatConfig,
+    pub speech: SpeechConfig,
+    pub logging: LoggingConfig,
+    /// Named bundles of transmission settings, referenced by a request's `profile` field
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Named message texts with `{{var}}` placeholders, referenced by a request's `template`
+    pub templates: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    /// Generate speech but skip audio playback (WSL, servers, testing)
+    pub headless: bool,
+    pub database_path: String,
+    /// Transmissions older than this are dropped instead of restored after a restart
+    pub queue_ttl_secs: Option<u64>,
+    pub schedules_file: Option<PathBuf>,
+    pub webhooks_file: Option<PathBuf>,
+    /// Required as "Authorization: Bearer <token>" by the routes that change server
+    /// state (schedules, webhooks, lexicon, reload, Do Not Disturb) when set
+    pub admin_token: Option<String>,
+    /// Pronunciation lexicon (JSON array), kept up to date with /lexicon API changes
+    pub lexicon_file: Option<PathBuf>,
+    /// Seconds GET /ready reuses its TTS provider and audio device checks
+    pub ready_probe_ttl_secs: u64,
+    /// Reload automatically when the config file or .env changes
+    pub watch_config: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:42069".to_string(),
+            headless: false,
+            database_path: "quindar.db".to_string(),
+            queue_ttl_secs: None,
+            schedules_file: None,
+            webhooks_file: None,
+            admin_token: None,
+            lexicon_file: None,
+            ready_probe_ttl_secs: 60,
+            watch_config: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TtsConfig {
+    pub provider: TtsProvider,
+    /// Edge voice used for OpenAI voice names and unknown voices
+    pub edge_voice: String,
+    pub openai_api_key: Option<String>,
+    /// Maximum number of synthesized clips kept in the audio cache (0 disables caching)
+    pub audio_cache_size: usize,
+    /// Longer texts are synthesized in sentence chunks of about this many characters,
+    /// and playback starts with the first (0 synthesizes the whole text at once)
+    pub chunk_chars: usize,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        TtsConfig {
+            provider: TtsProvider::Edge,
+            edge_voice: "en-US-AndrewNeural".to_string(),
+            openai_api_key: None,
+            audio_cache_size: 100,
+            chunk_chars: 250,
+        }
+    }
+}
+
+/// Used for requests that don't specify their own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultsConfig {
+    pub tone: ToneType,
+    pub enable_toast: bool,
+}
+
+impl Default for DefaultsConfig {
+    fn default() -> Self {
+        DefaultsConfig {
+            tone: ToneType::Quindar,
+            enable_toast: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AckConfig {
+    pub timeout_secs: u64,
+    pub max_escalations: u32,
+    /// Receives a JSON POST when an alert is never acknowledged
+    pub escalation_webhook: Option<String>,
+    /// Base URL used in ack links (default: derived from server.bind_address)
+    pub base_url: Option<String>,
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        AckConfig {
+            timeout_secs: 120,
+            max_escalations: 3,
+            escalation_webhook: None,
+            base_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertmanagerConfig {
+    /// Required as "Authorization: Bearer <token>" when set
+    pub token: Option<String>,
+    pub firing_template: String,
+    pub resolved_template: String,
+    pub send_resolved: bool,
+    /// Alerts spoken individually per notification; the rest are summarized
+    pub max_alerts: usize,
+}
+
+impl Default for AlertmanagerConfig {
+    fn default() -> Self {
+        AlertmanagerConfig {
+            token: None,
+            firing_template: "{{severity}} alert: {{alertname}}. {{summary}}".to_string(),
+            resolved_template: "Resolved: {{alertname}}. {{summary}}".to_string(),
+            send_resolved: true,
+            max_alerts: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+    /// Required as ?token=<value> on the Slack/Discord webhook shims when set
+    pub token: Option<String>,
+}
+
+/// How message text is rewritten before it's sent to the TTS provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeechConfig {
+    /// Strip Markdown, shorten URLs and paths, and expand acronyms (false sends text verbatim)
+    pub normalize: bool,
+    /// Longer messages are cut at a sentence boundary (0 never truncates)
+    pub max_chars: usize,
+    /// Words spoken as something else, e.g. k8s = "Kubernetes", added to the built-in list
+    pub acronyms: BTreeMap<String, String>,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        SpeechConfig {
+            normalize: true,
+            max_chars: 600,
+            acronyms: BTreeMap::new(),
+        }
+    }
+}
+
+/// Transmission settings applied to requests that name this profile. Fields the request
+/// sets itself take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// f64 so values print back exactly as written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    /// Voice pitch shift in Hz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<i32>,
+    /// Voice loudness change in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice_volume: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_toast: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toast_urgency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_ack: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ack_timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ack_max_escalations: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Level or RUST_LOG-style filter directives
+    pub level: String,
+    pub format: LogFormat,
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Rotated log files to keep (default: all)
+    pub max_files: Option<usize>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+            file: None,
+            rotation: LogRotation::Daily,
+            max_files: None,
+        }
+    }
+}
+
+/// A validated configuration and the file it came from
+pub struct Loaded {
+    pub config: Config,
+    pub source: Option<PathBuf>,
+}
+
+static CURRENT: LazyLock<RwLock<Arc<Config>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Config::default())));
+
+/// Variables from the .env file in effect. Kept apart from the process environment so a
+/// reload can pick up edits and removals without mutating it.
+static ENV_FILE: LazyLock<RwLock<Arc<HashMap<String, String>>>> = LazyLock::new(Default::default);
+
+/// The configuration in effect
+pub fn get() -> Arc<Config> {
+    CURRENT.read().unwrap().clone()
+}
+
+/// Make `config` the configuration in effect
+pub fn set(config: Config) {
+    *CURRENT.write().unwrap() = Arc::new(config);
+}
+
+/// Make `vars` the .env values used for environment overrides
+pub fn set_env_file(vars: HashMap<String, String>) {
+    *ENV_FILE.write().unwrap() = Arc::new(vars);
+}
+
+/// An environment variable, falling back to the .env file in effect
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .or_else(|| ENV_FILE.read().unwrap().get(name).cloned())
+}
+
+/// The .env file next to the executable, else the first one in the current directory or
+/// its parents
+pub fn find_env_file() -> Option<PathBuf> {
+    let exe_env = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(".env")))
+        .filter(|path| path.is_file());
+    exe_env.or_else(|| {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors()
+            .map(|dir| dir.join(".env"))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Read the .env file, if there is one, without touching the process environment
+// dotenv deprecates its iterator in favour of loading into the environment, which is
+// exactly what a reload must not do
+#[allow(deprecated)]
+pub fn read_env_file() -> Result<(Option<PathBuf>, HashMap<String, String>), String> {
+    let Some(path) = find_env_file() else {
+        return Ok((None, HashMap::new()));
+    };
+    let vars = dotenv::from_path_iter(&path)
+        .and_then(|iter| iter.collect::<Result<HashMap<_, _>, _>>())
+        .map_err(|e| format!("Found .env at {} but failed to load: {}", path.display(), e))?;
+    Ok((Some(path), vars))
+}
+
+/// The file named by --config or QUINDAR_CONFIG, else quindar.toml if one exists
+fn config_file(path: Option<&Path>, env: &Env) -> Result<Option<PathBuf>, String> {
+    let named = path
+        .map(Path::to_path_buf)
+        .or_else(|| env.var("QUINDAR_CONFIG").map(PathBuf::from));
+    if let Some(path) = named {
+        return if path.is_file() {
+            Ok(Some(path))
+        } else {
+            Err(format!("Config file {} not found", path.display()))
+        };
+    }
+
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(DEFAULT_CONFIG_FILE)));
+    Ok(exe_dir
+        .into_iter()
+        .chain([PathBuf::from(DEFAULT_CONFIG_FILE)])
+        .find(|path| path.is_file()))
+}
+
+/// Read the config file (if any), apply environment overrides and validate the result,
+/// returning every problem found rather than just the first
+pub fn load(path: Option<&Path>) -> Result<Loaded, Vec<String>> {
+    let env_file = ENV_FILE.read().unwrap().clone();
+    load_with_env_file(path, &env_file)
+}
+
+/// [`load`], taking .env values from `env_file` instead of the ones in effect
+pub fn load_with_env_file(
+    path: Option<&Path>,
+    env_file: &HashMap<String, String>,
+) -> Result<Loaded, Vec<String>> {
+    let mut env = Env {
+        file: env_file,
+        errors: Vec::new(),
+    };
+    let source = config_file(path, &env).map_err(|e| vec![e])?;
+
+    let mut config = match &source {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| vec![format!("Failed to read {}: {}", path.display(), e)])?;
+            toml::from_str(&contents).map_err(|e| vec![format!("{}: {}", path.display(), e)])?
+        }
+        None => Config::default(),
+    };
+
+    config.apply_env(&mut env);
+    let mut errors = env.errors;
+    config.validate(&mut errors);
+
+    if errors.is_empty() {
+        Ok(Loaded { config, source })
+    } else {
+        Err(errors)
+    }
+}
+
+/// Print configuration problems to stderr
+pub fn report_errors(errors: &[String]) {
+    eprintln!("Invalid configuration:");
+    for error in errors {
+        eprintln!("  - {}", error);
+    }
+}
+
+/// --print-config: show the effective settings, or the problems with them
+pub fn print(path: Option<&Path>) {
+    match load(path) {
+        Ok(loaded) => {
+            match &loaded.source {
+                Some(path) => println!(
+                    "# Effective configuration: {} with environment overrides",
+                    path.display()
+                ),
+                None => println!(
+                    "# Effective configuration: defaults with environment overrides (no config file)"
+                ),
+            }
+            print!("{}", loaded.config.to_toml_redacted());
+        }
+        Err(errors) => {
+            report_errors(&errors);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Environment overrides: the process environment, then values read from .env
+struct Env<'a> {
+    file: &'a HashMap<String, String>,
+    errors: Vec<String>,
+}
+
+impl Env<'_> {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .or_else(|| self.file.get(name).cloned())
+    }
+
+    /// Overwrite `target` from environment variable `name`, if it is set
+    fn apply<T>(
+        &mut self,
+        name: &str,
+        target: &mut T,
+        expected: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) {
+        let Some(value) = self.var(name) else {
+            return;
+        };
+        match parse(value.trim()) {
+            Some(parsed) => *target = parsed,
+            None => self.errors.push(format!(
+                "{}: invalid value '{}' (expected {})",
+                name, value, expected
+            )),
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+/// An empty value clears an optional setting
+fn parse_optional(value: &str) -> Option<Option<String>> {
+    Some((!value.is_empty()).then(|| value.to_string()))
+}
+
+fn parse_optional_path(value: &str) -> Option<Option<PathBuf>> {
+    Some((!value.is_empty()).then(|| PathBuf::from(value)))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+fn parse_optional_number<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    if value.is_empty() {
+        Some(None)
+    } else {
+        value.parse().ok().map(Some)
+    }
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+impl Config {
+    /// Environment variables keep their historical names and win over the file
+    fn apply_env(&mut self, env: &mut Env) {
+        const BOOL: &str = "true or false";
+        const NUMBER: &str = "a non-negative whole number";
+
+        let server = &mut self.server;
+        env.apply(
+            "BIND_ADDRESS",
+            &mut server.bind_address,
+            "host:port",
+            parse_string,
+        );
+        env.apply("HEADLESS_MODE", &mut server.headless, BOOL, parse_bool);
+        env.apply(
+            "DATABASE_PATH",
+            &mut server.database_path,
+            "a path",
+            parse_string,
+        );
+        env.apply(
+            "QUEUE_TTL_SECS",
+            &mut server.queue_ttl_secs,
+            NUMBER,
+            parse_optional_number,
+        );
+        env.apply(
+            "SCHEDULES_FILE",
+            &mut server.schedules_file,
+            "a path",
+            parse_optional_path,
+        );
+        env.apply(
+            "WEBHOOKS_FILE",
+            &mut server.webhooks_file,
+            "a path",
+            parse_optional_path,
+        );
+        env.apply(
+            "ADMIN_TOKEN",
+            &mut server.admin_token,
+            "a token",
+            parse_optional,
+        );
+        env.apply(
+            "LEXICON_FILE",
+            &mut server.lexicon_file,
+            "a path",
+            parse_optional_path,
+        );
+        env.apply(
+            "READY_PROBE_TTL_SECS",
+            &mut server.ready_probe_ttl_secs,
+            NUMBER,
+            parse_number,
+        );
+        env.apply("WATCH_CONFIG", &mut server.watch_config, BOOL, parse_bool);
+
+        let tts = &mut self.tts;
+        env.apply(
+            "DEFAULT_TTS",
+            &mut tts.provider,
+            "EDGE or OPENAI",
+            TtsProvider::parse,
+        );
+        env.apply(
+            "EDGE_VOICE",
+            &mut tts.edge_voice,
+            "a voice name",
+            parse_string,
+        );
+        env.apply(
+            "OPENAI_API_KEY",
+            &mut tts.openai_api_key,
+            "an API key",
+            parse_optional,
+        );
+        env.apply(
+            "AUDIO_CACHE_SIZE",
+            &mut tts.audio_cache_size,
+            NUMBER,
+            parse_number,
+        );
+        env.apply(
+            "TTS_CHUNK_CHARS",
+            &mut tts.chunk_chars,
+            NUMBER,
+            parse_number,
+        );
+
+        let defaults = &mut self.defaults;
+        env.apply(
+            "DEFAULT_TONE",
+            &mut defaults.tone,
+            "QUINDAR, THREE-NOTE-CHIME or NO-TONE",
+            ToneType::parse,
+        );
+        env.apply(
+            "ENABLE_TOAST_NOTIFICATIONS",
+            &mut defaults.enable_toast,
+            BOOL,
+            parse_bool,
+        );
+
+        let acks = &mut self.acks;
+        env.apply(
+            "ACK_TIMEOUT_SECS",
+            &mut acks.timeout_secs,
+            NUMBER,
+            parse_number,
+        );
+        env.apply(
+            "ACK_MAX_ESCALATIONS",
+            &mut acks.max_escalations,
+            NUMBER,
+            parse_number,
+        );
+        env.apply(
+            "ACK_ESCALATION_WEBHOOK",
+            &mut acks.escalation_webhook,
+            "a URL",
+            parse_optional,
+        );
+        env.apply("ACK_BASE_URL", &mut acks.base_url, "a URL", parse_optional);
+
+        let alertmanager = &mut self.alertmanager;
+        env.apply(
+            "ALERTMANAGER_TOKEN",
+            &mut alertmanager.token,
+            "a token",
+            parse_optional,
+        );
+        env.apply(
+            "ALERTMANAGER_TEMPLATE",
+            &mut alertmanager.firing_template,
+            "a template",
+            parse_string,
+        );
+        env.apply(
+            "ALERTMANAGER_RESOLVED_TEMPLATE",
+            &mut alertmanager.resolved_template,
+            "a template",
+            parse_string,
+        );
+        env.apply(
+            "ALERTMANAGER_SEND_RESOLVED",
+            &mut alertmanager.send_resolved,
+            BOOL,
+            parse_bool,
+        );
+        env.apply(
+            "ALERTMANAGER_MAX_ALERTS",
+            &mut alertmanager.max_alerts,
+            NUMBER,
+            parse_number,
+        );
+
+        env.apply(
+            "CHAT_WEBHOOK_TOKEN",
+            &mut self.chat.token,
+            "a token",
+            parse_optional,
+        );
+
+        let speech = &mut self.speech;
+        env.apply("SPEECH_NORMALIZE", &mut speech.normalize, BOOL, parse_bool);
+        env.apply(
+            "SPEECH_MAX_CHARS",
+            &mut speech.max_chars,
+            NUMBER,
+            parse_number,
+        );
+
+        let logging = &mut self.logging;
+        env.apply(
+            "RUST_LOG",
+            &mut logging.level,
+            "filter directives",
+            parse_string,
+        );
+        env.apply(
+            "LOG_FORMAT",
+            &mut logging.format,
+            "pretty, compact or json",
+            LogFormat::parse,
+        );
+        env.apply("LOG_FILE", &mut logging.file, "a path", parse_optional_path);
+        env.apply(
+            "LOG_ROTATION",
+            &mut logging.rotation,
+            "minutely, hourly, daily or never",
+            LogRotation::parse,
+        );
+        env.apply(
+            "LOG_MAX_FILES",
+            &mut logging.max_files,
+            NUMBER,
+            parse_optional_number,
+        );
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        let port = self
+            .server
+            .bind_address
+            .rsplit_once(':')
+            .filter(|(host, _)| !host.is_empty())
+            .and_then(|(_, port)| port.parse::<u16>().ok());
+        if port.is_none() {
+            errors.push(format!(
+                "server.bind_address: '{}' is not a host:port address",
+                self.server.bind_address
+            ));
+        }
+        if self.server.queue_ttl_secs == Some(0) {
+            errors.push("server.queue_ttl_secs: must be at least 1 (omit it to keep transmissions indefinitely)".to_string());
+        }
+
+        if self.tts.provider == TtsProvider::OpenAI && self.tts.openai_api_key.is_none() {
+            errors.push(
+                "tts.openai_api_key: required when tts.provider is openai (or set OPENAI_API_KEY)"
+                    .to_string(),
+            );
+        }
+        if self.tts.edge_voice.trim().is_empty() {
+            errors.push("tts.edge_voice: must not be empty".to_string());
+        }
+
+        if self.acks.timeout_secs == 0 {
+            errors.push("acks.timeout_secs: must be at least 1".to_string());
+        }
+        for (key, url) in [
+            ("acks.escalation_webhook", &self.acks.escalation_webhook),
+            ("acks.base_url", &self.acks.base_url),
+        ] {
+            if let Some(url) = url.as_deref().filter(|url| !is_http_url(url)) {
+                errors.push(format!("{}: '{}' is not an http(s) URL", key, url));
+            }
+        }
+
+        if self.alertmanager.max_alerts == 0 {
+            errors.push("alertmanager.max_alerts: must be at least 1".to_string());
+        }
+        for (key, template) in [
+            (
+                "alertmanager.firing_template",
+                &self.alertmanager.firing_template,
+            ),
+            (
+                "alertmanager.resolved_template",
+                &self.alertmanager.resolved_template,
+            ),
+        ] {
+            if template.trim().is_empty() {
+                errors.push(format!("{}: must not be empty", key));
+            } else if let Err(e) = crate::templates::validate(template) {
+                errors.push(format!("{}: {}", key, e));
+            }
+        }
+
+        for (name, profile) in &self.profiles {
+            let key = format!("profiles.{}", name);
+            if name.trim().is_empty() {
+                errors.push("profiles: profile names must not be empty".to_string());
+            }
+            if let Some(tone) = profile
+                .tone
+                .as_deref()
+                .filter(|tone| ToneType::parse(tone).is_none())
+            {
+                errors.push(format!(
+                    "{}.tone: invalid value '{}' (expected QUINDAR, THREE-NOTE-CHIME or NO-TONE)",
+                    key, tone
+                ));
+            }
+            if let Some(urgency) = profile
+                .toast_urgency
+                .as_deref()
+                .filter(|urgency| ToastUrgency::parse(urgency).is_none())
+            {
+                errors.push(format!(
+                    "{}.toast_urgency: invalid value '{}' (expected info, warning or critical)",
+                    key, urgency
+                ));
+            }
+            if let Some(speed) = profile.speed.filter(|speed| !(0.25..=4.0).contains(speed)) {
+                errors.push(format!(
+                    "{}.speed: {} is out of range (0.25 to 4.0)",
+                    key, speed
+                ));
+            }
+            // Requests check volume as f32, so compare the same way
+            if let Some(volume) = profile
+                .volume
+                .filter(|volume| !crate::VOLUME_RANGE.contains(&(*volume as f32)))
+            {
+                errors.push(format!(
+                    "{}.volume: {} is out of range ({} to {})",
+                    key,
+                    volume,
+                    crate::VOLUME_RANGE.start(),
+                    crate::VOLUME_RANGE.end()
+                ));
+            }
+            for (field, value) in [
+                ("pitch", profile.pitch),
+                ("voice_volume", profile.voice_volume),
+            ] {
+                if let Some(value) = value.filter(|value| !crate::PROSODY_RANGE.contains(value)) {
+                    errors.push(format!(
+                        "{}.{}: {} is out of range ({} to {})",
+                        key,
+                        field,
+                        value,
+                        crate::PROSODY_RANGE.start(),
+                        crate::PROSODY_RANGE.end()
+                    ));
+                }
+            }
+            if profile.ack_timeout_secs == Some(0) {
+                errors.push(format!("{}.ack_timeout_secs: must be at least 1", key));
+            }
+        }
+
+        for (name, template) in &self.templates {
+            if name.trim().is_empty() {
+                errors.push("templates: template names must not be empty".to_string());
+            }
+            if let Err(e) = crate::templates::validate(template) {
+                errors.push(format!("templates.{}: {}", name, e));
+            }
+        }
+
+        for (word, spoken) in &self.speech.acronyms {
+            if word.is_empty() || word.contains(char::is_whitespace) {
+                errors.push(format!(
+                    "speech.acronyms: '{}' must be a single word without spaces",
+                    word
+                ));
+            }
+            if spoken.trim().is_empty() {
+                errors.push(format!("speech.acronyms.{}: must not be empty", word));
+            }
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.logging.level) {
+            errors.push(format!(
+                "logging.level: '{}' is invalid: {}",
+                self.logging.level, e
+            ));
+        }
+        if let Some(file) = self
+            .logging
+            .file
+            .as_ref()
+            .filter(|f| f.file_name().is_none())
+        {
+            errors.push(format!(
+                "logging.file: '{}' is not a file path",
+                file.display()
+            ));
+        }
+        if self.logging.max_files == Some(0) {
+            errors.push(
+                "logging.max_files: must be at least 1 (omit it to keep every file)".to_string(),
+            );
+        }
+    }
+
+    /// Keep the running values of settings that are only read at startup, returning the
+    /// keys whose new values will wait for a restart
+    pub fn keep_startup_settings(&mut self, running: &Config) -> Vec<String> {
+        let changed = self.changed_keys(running);
+        self.server.bind_address = running.server.bind_address.clone();
+        self.server.database_path = running.server.database_path.clone();
+        self.server.queue_ttl_secs = running.server.queue_ttl_secs;
+        self.server.watch_config = running.server.watch_config;
+        self.tts.audio_cache_size = running.tts.audio_cache_size;
+        self.logging.format = running.logging.format;
+        self.logging.file = running.logging.file.clone();
+        self.logging.rotation = running.logging.rotation;
+        self.logging.max_files = running.logging.max_files;
+
+        let still_changed = self.changed_keys(running);
+        changed
+            .into_iter()
+            .filter(|key| !still_changed.contains(key))
+            .collect()
+    }
+
+    /// Dotted keys (e.g. `tts.edge_voice`) whose values differ from `other`
+    pub fn changed_keys(&self, other: &Config) -> Vec<String> {
+        let (ours, theirs) = (flatten(self), flatten(other));
+        let mut keys: Vec<String> = ours
+            .keys()
+            .chain(theirs.keys())
+            .filter(|key| ours.get(*key) != theirs.get(*key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// TOML for --print-config, with tokens and API keys hidden
+    pub fn to_toml_redacted(&self) -> String {
+        let mut config = self.clone();
+        for secret in [
+            &mut config.tts.openai_api_key,
+            &mut config.server.admin_token,
+            &mut config.alertmanager.token,
+            &mut config.chat.token,
+        ] {
+            if secret.is_some() {
+                *secret = Some(REDACTED.to_string());
+            }
+        }
+        toml::to_string_pretty(&config).unwrap_or_else(|e| format!("# Failed to render: {}\n", e))
+    }
+}
+
+/// Every setting as `section.key` -> value; unset optional settings are left out
+fn flatten(config: &Config) -> HashMap<String, toml::Value> {
+    let Ok(toml::Value::Table(sections)) = toml::Value::try_from(config) else {
+        return HashMap::new();
+    };
+    sections
+        .into_iter()
+        .flat_map(|(section, values)| match values {
+            toml::Value::Table(values) => values
+                .into_iter()
+                .map(|(key, value)| (format!("{}.{}", section, key), value))
+                .collect::<Vec<_>>(),
+            value => vec![(section, value)],
+        })
+        .collect()
+}
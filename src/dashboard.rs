@@ -0,0 +1,10 @@
+//! Embedded web dashboard, served from the binary at GET /
+
+use axum::response::Html;
+
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// GET / - queue, history, test messages and live events
+pub async fn dashboard_handler() -> Html<&'static str> {
+    Html(DASHBOARD_HTML)
+}
@@ -0,0 +1,44 @@
+//! Do Not Disturb: hold non-critical transmissions until switched off
+
+use crate::AppState;
+use axum::{
+    extract::{Json, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Serialize, Deserialize)]
+pub struct DndStatus {
+    pub enabled: bool,
+}
+
+/// GET /dnd - whether Do Not Disturb is on
+pub async fn dnd_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(DndStatus {
+        enabled: *state.dnd.borrow(),
+    })
+}
+
+/// PUT /dnd - switch Do Not Disturb on or off
+pub async fn set_dnd_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(status): Json<DndStatus>,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection.into_response();
+    }
+    state.dnd.send_replace(status.enabled);
+    info!(
+        "Do not disturb {}",
+        if status.enabled {
+            "on - holding non-critical transmissions"
+        } else {
+            "off"
+        }
+    );
+    Json(status).into_response()
+}
@@ -0,0 +1,173 @@
+//! Transmission lifecycle events, streamed to clients over Server-Sent Events
+
+use crate::queue::QueueEntry;
+use crate::{AppState, TransmissionRequest};
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+/// Events buffered per subscriber before a slow client starts missing them
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Queued,
+    Synthesizing,
+    Playing,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Queued => "queued",
+            EventKind::Synthesizing => "synthesizing",
+            EventKind::Playing => "playing",
+            EventKind::Completed => "completed",
+            EventKind::Failed => "failed",
+            EventKind::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransmissionEvent {
+    pub event: EventKind,
+    pub id: String,
+    pub text: String,
+    pub client: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub queued_at: DateTime<Utc>,
+    /// Milliseconds since the transmission was queued
+    pub elapsed_ms: i64,
+    /// Milliseconds spent processing, on completed/failed/cancelled events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The transmission an event is about
+struct Subject<'a> {
+    id: &'a str,
+    text: &'a str,
+    client: &'a Option<String>,
+    queued_at: DateTime<Utc>,
+}
+
+impl<'a> From<&'a TransmissionRequest> for Subject<'a> {
+    fn from(req: &'a TransmissionRequest) -> Self {
+        Subject {
+            id: &req.id,
+            text: &req.text,
+            client: &req.client,
+            queued_at: req.queued_at,
+        }
+    }
+}
+
+impl<'a> From<&'a QueueEntry> for Subject<'a> {
+    fn from(entry: &'a QueueEntry) -> Self {
+        Subject {
+            id: &entry.id,
+            text: &entry.text,
+            client: &entry.client,
+            queued_at: entry.queued_at,
+        }
+    }
+}
+
+/// Fan-out of lifecycle events to every connected subscriber
+pub struct EventBus {
+    tx: broadcast::Sender<TransmissionEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUFFER);
+        EventBus { tx }
+    }
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> broadcast::Receiver<TransmissionEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn emit(&self, kind: EventKind, req: &TransmissionRequest) {
+        self.send(kind, req.into(), None, None);
+    }
+
+    /// Emit an event for a transmission known only by its queue entry
+    pub fn emit_entry(&self, kind: EventKind, entry: &QueueEntry) {
+        self.send(kind, entry.into(), None, None);
+    }
+
+    /// Emit a terminal event with the processing time (and error, if any)
+    pub fn finish(
+        &self,
+        kind: EventKind,
+        req: &TransmissionRequest,
+        started_at: DateTime<Utc>,
+        error: Option<String>,
+    ) {
+        let duration_ms = (Utc::now() - started_at).num_milliseconds();
+        self.send(kind, req.into(), Some(duration_ms), error);
+    }
+
+    fn send(
+        &self,
+        kind: EventKind,
+        subject: Subject,
+        duration_ms: Option<i64>,
+        error: Option<String>,
+    ) {
+        let now = Utc::now();
+        // Nobody listening is fine - send only fails when there are no subscribers
+        let _ = self.tx.send(TransmissionEvent {
+            event: kind,
+            id: subject.id.to_string(),
+            text: subject.text.to_string(),
+            client: subject.client.clone(),
+            timestamp: now,
+            queued_at: subject.queued_at,
+            elapsed_ms: (now - subject.queued_at).num_milliseconds(),
+            duration_ms,
+            error,
+        });
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventQuery {
+    /// Only stream events for this transmission ID
+    pub id: Option<String>,
+}
+
+/// GET /events - Server-Sent Events stream of transmission lifecycle events
+pub async fn events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(move |event| {
+        // A lagging client skips the events it missed rather than disconnecting
+        let event = event.ok()?;
+        if query.id.as_ref().is_some_and(|id| *id != event.id) {
+            return None;
+        }
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok(Event::default().event(event.event.as_str()).data(data)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,211 @@
+//! Liveness and readiness checks for load balancers, supervisors and monitoring
+
+use crate::{AppState, TtsProvider};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Give up on a provider that doesn't answer within this long
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize)]
+pub struct ComponentStatus {
+    pub healthy: bool,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// When the underlying probe ran - cached results keep their original time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl ComponentStatus {
+    fn new(healthy: bool, detail: impl Into<String>) -> Self {
+        ComponentStatus {
+            healthy,
+            detail: detail.into(),
+            latency_ms: None,
+            checked_at: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Components {
+    pub tts: ComponentStatus,
+    pub audio: ComponentStatus,
+    pub queue_processor: ComponentStatus,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub provider: String,
+    pub components: Components,
+}
+
+struct CachedProbe {
+    key: String,
+    at: Instant,
+    status: ComponentStatus,
+}
+
+/// Last TTS and audio probe results, so frequent readiness polls don't hammer either
+static TTS_PROBE: Mutex<Option<CachedProbe>> = Mutex::new(None);
+static AUDIO_PROBE: Mutex<Option<CachedProbe>> = Mutex::new(None);
+
+/// How long a probe result is reused before the provider or device is checked again
+fn probe_ttl() -> Duration {
+    Duration::from_secs(crate::config::get().server.ready_probe_ttl_secs)
+}
+
+fn cached(cache: &Mutex<Option<CachedProbe>>, key: &str) -> Option<ComponentStatus> {
+    cache
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|probe| probe.key == key && probe.at.elapsed() < probe_ttl())
+        .map(|probe| probe.status.clone())
+}
+
+/// Run `probe` unless a fresh result for the same key is cached
+async fn cached_probe<F>(cache: &Mutex<Option<CachedProbe>>, key: &str, probe: F) -> ComponentStatus
+where
+    F: Future<Output = Result<String, String>>,
+{
+    if let Some(status) = cached(cache, key) {
+        return status;
+    }
+
+    let started = Instant::now();
+    let result = probe.await;
+    let status = ComponentStatus {
+        healthy: result.is_ok(),
+        detail: result.unwrap_or_else(|e| e),
+        latency_ms: Some(started.elapsed().as_millis() as u64),
+        checked_at: Some(Utc::now()),
+    };
+    *cache.lock().unwrap() = Some(CachedProbe {
+        key: key.to_string(),
+        at: Instant::now(),
+        status: status.clone(),
+    });
+    status
+}
+
+/// Open (and close) a connection to the Edge TTS service
+async fn probe_edge() -> Result<String, String> {
+    match tokio::time::timeout(PROBE_TIMEOUT, msedge_tts::tts::client::connect_async()).await {
+        Ok(Ok(_)) => Ok("Edge TTS reachable".to_string()),
+        Ok(Err(e)) => Err(format!("Failed to connect to Edge TTS: {}", e)),
+        Err(_) => Err("Edge TTS did not respond in time".to_string()),
+    }
+}
+
+/// Check the API key against the OpenAI models endpoint - no audio is generated or billed
+async fn probe_openai() -> Result<String, String> {
+    let api_key = crate::config::get()
+        .tts
+        .openai_api_key
+        .clone()
+        .ok_or_else(|| "OPENAI_API_KEY not set but DEFAULT_TTS=OPENAI".to_string())?;
+
+    let response = reqwest::Client::new()
+        .get("https://api.openai.com/v1/models/tts-1")
+        .bearer_auth(api_key)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach OpenAI: {}", e))?;
+
+    if response.status().is_success() {
+        Ok("OpenAI reachable".to_string())
+    } else {
+        Err(format!("OpenAI returned {}", response.status()))
+    }
+}
+
+/// Open the default audio output device, then release it
+async fn probe_audio() -> Result<String, String> {
+    tokio::task::spawn_blocking(|| {
+        rodio::OutputStream::try_default()
+            .map(|_| "Audio output available".to_string())
+            .map_err(|e| {
+                crate::metrics::METRICS.audio_device_error();
+                format!("Failed to open audio output: {}", e)
+            })
+    })
+    .await
+    .map_err(|e| format!("Audio probe failed: {}", e))?
+}
+
+async fn tts_status(provider: &TtsProvider) -> ComponentStatus {
+    match provider {
+        TtsProvider::Edge => cached_probe(&TTS_PROBE, provider.as_str(), probe_edge()).await,
+        TtsProvider::OpenAI => cached_probe(&TTS_PROBE, provider.as_str(), probe_openai()).await,
+    }
+}
+
+async fn audio_status(state: &AppState) -> ComponentStatus {
+    if crate::is_headless_mode() {
+        return ComponentStatus::new(true, "Headless mode - audio playback disabled");
+    }
+    // Don't compete with a transmission for the device - it's evidently working
+    if state.queue.status().current.is_some() {
+        return cached(&AUDIO_PROBE, "default").unwrap_or_else(|| {
+            ComponentStatus::new(true, "Audio output in use by the current transmission")
+        });
+    }
+    cached_probe(&AUDIO_PROBE, "default", probe_audio()).await
+}
+
+fn queue_processor_status(state: &AppState) -> ComponentStatus {
+    // The processor owns the receiving end of the queue, so a closed channel means it has exited
+    if state.tx.is_closed() {
+        ComponentStatus::new(false, "Queue processor has stopped")
+    } else {
+        ComponentStatus::new(true, "Queue processor running")
+    }
+}
+
+/// GET /health - the process is up and serving requests
+pub async fn health_handler() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+/// GET /ready - 200 when the TTS provider, audio output and queue processor all work, else 503
+pub async fn ready_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let provider = TtsProvider::configured();
+    let (tts, audio) = tokio::join!(tts_status(&provider), audio_status(&state));
+    let components = Components {
+        tts,
+        audio,
+        queue_processor: queue_processor_status(&state),
+    };
+
+    let ready =
+        components.tts.healthy && components.audio.healthy && components.queue_processor.healthy;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            provider: provider.as_str().to_lowercase(),
+            components,
+        }),
+    )
+}
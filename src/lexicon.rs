@@ -0,0 +1,364 @@
+//! Pronunciation lexicon: words the TTS providers get wrong, each with a phonetic
+//! respelling and/or an SSML phoneme, applied to the text before synthesis
+
+use crate::persisted::{Named, NamedStore};
+use crate::{AppState, TtsProvider};
+use axum::{
+    extract::{Json, Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Phonetic alphabets accepted in SSML `<phoneme>` elements
+const ALPHABETS: &[&str] = &["ipa", "x-sampa", "sapi", "ups"];
+
+/// How to say one word. Edge uses `phoneme` when set; OpenAI, which takes no SSML, uses
+/// `respelling`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LexiconEntry {
+    pub word: String,
+    /// Sounds-like spelling, e.g. "KWIN-dar"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub respelling: Option<String>,
+    /// Pronunciation in `alphabet`, e.g. "ˈkwɪndɑr"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phoneme: Option<String>,
+    #[serde(default = "default_alphabet")]
+    pub alphabet: String,
+    /// Match the word's capitalization exactly (default: any case)
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+fn default_alphabet() -> String {
+    "ipa".to_string()
+}
+
+/// Lexicon entries shared between the management API and the transmission pipeline,
+/// with the matcher built from them
+pub struct LexiconStore {
+    pub entries: NamedStore<LexiconEntry>,
+    /// The matcher and the store generation it was built for
+    matcher: Mutex<Option<(u64, Arc<Matcher>)>>,
+}
+
+/// Every entry's word compiled once: `any` finds where some word starts, `each` (same
+/// order as `entries`, longest first) checks which one fits there
+struct Matcher {
+    entries: Vec<LexiconEntry>,
+    any: Regex,
+    each: Vec<Regex>,
+}
+
+/// Check an entry's word, pronunciations and alphabet
+fn validate(entry: &LexiconEntry) -> Result<(), String> {
+    if entry.word.trim().is_empty() {
+        return Err("Lexicon word must not be empty".to_string());
+    }
+    if entry.word != entry.word.trim() {
+        return Err(format!(
+            "Lexicon word '{}' must not start or end with spaces",
+            entry.word
+        ));
+    }
+
+    let respelling = entry.respelling.as_deref().map(str::trim);
+    let phoneme = entry.phoneme.as_deref().map(str::trim);
+    if respelling.is_none() && phoneme.is_none() {
+        return Err(format!(
+            "Lexicon word '{}' needs a respelling, a phoneme or both",
+            entry.word
+        ));
+    }
+    if respelling == Some("") || phoneme == Some("") {
+        return Err(format!(
+            "Lexicon word '{}' has an empty respelling or phoneme",
+            entry.word
+        ));
+    }
+    if !ALPHABETS.contains(&entry.alphabet.as_str()) {
+        return Err(format!(
+            "Lexicon word '{}' has unknown alphabet '{}' (expected {})",
+            entry.word,
+            entry.alphabet,
+            ALPHABETS.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Escape text for use inside SSML content or a quoted attribute
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl Named for LexiconEntry {
+    type Spec = LexiconEntry;
+    const KIND: &'static str = "Lexicon entry";
+    const FILE: &'static str = "lexicon file";
+
+    fn compile(entry: LexiconEntry) -> Result<Self, String> {
+        validate(&entry)?;
+        Ok(entry)
+    }
+
+    fn spec(&self) -> &LexiconEntry {
+        self
+    }
+
+    fn name(entry: &LexiconEntry) -> &str {
+        &entry.word
+    }
+}
+
+impl Matcher {
+    fn new(mut entries: Vec<LexiconEntry>) -> Result<Self, regex::Error> {
+        // Longest first, so "Quindar API" wins over "Quindar"
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.word.len()));
+
+        let words: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let flags = if entry.case_sensitive { "" } else { "i" };
+                format!("(?{}:{})", flags, regex::escape(&entry.word))
+            })
+            .collect();
+        let each = words
+            .iter()
+            .map(|word| Regex::new(&format!(r"\A{}", word)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Matcher {
+            any: Regex::new(&words.join("|"))?,
+            each,
+            entries,
+        })
+    }
+
+    /// The longest entry matching a whole word at `start`, with the matched text
+    fn at<'t>(&self, text: &'t str, start: usize) -> Option<(&LexiconEntry, &'t str)> {
+        // Only whole words: "Quindar" shouldn't change inside "Quindars"
+        if text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+        {
+            return None;
+        }
+        self.each
+            .iter()
+            .zip(&self.entries)
+            .find_map(|(word, entry)| {
+                let matched = word.find(&text[start..])?;
+                let end = start + matched.end();
+                let after = text[end..].chars().next();
+                (!after.is_some_and(char::is_alphanumeric)).then_some((entry, &text[start..end]))
+            })
+    }
+}
+
+impl LexiconStore {
+    /// Load entries from `path` (JSON array), if one is configured
+    pub fn load(path: Option<PathBuf>) -> Self {
+        LexiconStore {
+            entries: NamedStore::load(path),
+            matcher: Mutex::new(None),
+        }
+    }
+
+    /// The matcher for the current entries, rebuilt only after they change
+    fn matcher(&self) -> Option<Arc<Matcher>> {
+        let generation = self.entries.generation();
+        let mut cached = self.matcher.lock().unwrap();
+        if let Some((_, matcher)) = cached.as_ref().filter(|(built, _)| *built == generation) {
+            return Some(matcher.clone());
+        }
+
+        let entries = self.entries.with_items(<[LexiconEntry]>::to_vec);
+        match Matcher::new(entries) {
+            Ok(matcher) => {
+                let matcher = Arc::new(matcher);
+                *cached = Some((generation, matcher.clone()));
+                Some(matcher)
+            }
+            Err(e) => {
+                warn!("Failed to build lexicon pattern: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Rewrite `text` for the provider: SSML with `<phoneme>` elements for Edge, plain
+    /// text with respellings for OpenAI
+    pub fn apply(&self, text: &str, provider: &TtsProvider) -> String {
+        let ssml = *provider == TtsProvider::Edge;
+        let output = |plain: &str| {
+            if ssml {
+                escape_xml(plain)
+            } else {
+                plain.to_string()
+            }
+        };
+
+        if self.entries.is_empty() {
+            return output(text);
+        }
+        let Some(matcher) = self.matcher() else {
+            return output(text);
+        };
+
+        let mut result = String::new();
+        let mut copied = 0;
+        let mut search = 0;
+        while let Some(found) = matcher.any.find_at(text, search) {
+            let start = found.start();
+            let Some((entry, word)) = matcher.at(text, start) else {
+                search = start + text[start..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            result.push_str(&output(&text[copied..start]));
+            result.push_str(&pronounce(entry, word, ssml));
+            copied = start + word.len();
+            search = copied;
+        }
+        result.push_str(&output(&text[copied..]));
+        result
+    }
+}
+
+/// The spoken form of one occurrence of an entry's word
+fn pronounce(entry: &LexiconEntry, word: &str, ssml: bool) -> String {
+    match (&entry.phoneme, &entry.respelling) {
+        (Some(phoneme), _) if ssml => format!(
+            "<phoneme alphabet=\"{}\" ph=\"{}\">{}</phoneme>",
+            escape_xml(&entry.alphabet),
+            escape_xml(phoneme),
+            escape_xml(word)
+        ),
+        (_, Some(respelling)) if ssml => escape_xml(respelling),
+        (_, Some(respelling)) => respelling.clone(),
+        // Only a phoneme, which plain text can't carry
+        _ if ssml => escape_xml(word),
+        _ => word.to_string(),
+    }
+}
+
+/// GET /lexicon - list pronunciation entries
+pub async fn list_lexicon_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.lexicon.entries.with_items(<[LexiconEntry]>::to_vec))
+}
+
+/// POST /lexicon - create or replace the entry for a word
+pub async fn create_lexicon_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(entry): Json<LexiconEntry>,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection;
+    }
+    state.lexicon.entries.create_response(entry)
+}
+
+/// DELETE /lexicon/{word} - remove the entry for a word
+pub async fn delete_lexicon_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection;
+    }
+    state.lexicon.entries.delete_response(&word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(entries: &[(&str, &str, bool)]) -> LexiconStore {
+        let store = LexiconStore::load(None);
+        for (word, respelling, case_sensitive) in entries {
+            store
+                .entries
+                .insert(LexiconEntry {
+                    word: word.to_string(),
+                    respelling: Some(respelling.to_string()),
+                    phoneme: None,
+                    alphabet: default_alphabet(),
+                    case_sensitive: *case_sensitive,
+                })
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn longest_entry_wins() {
+        let lexicon = store(&[
+            ("Quindar", "KWIN-dar", false),
+            ("Quindar API", "KWIN-dar A-P-I", false),
+        ]);
+        assert_eq!(
+            lexicon.apply("the Quindar API is up", &TtsProvider::OpenAI),
+            "the KWIN-dar A-P-I is up"
+        );
+    }
+
+    #[test]
+    fn shorter_entry_after_longer_one_is_rejected() {
+        let lexicon = store(&[
+            ("Quindar", "KWIN-dar", false),
+            ("Quindar API", "KWIN-dar A-P-I", false),
+        ]);
+        assert_eq!(
+            lexicon.apply("Quindar APIs, Quindars", &TtsProvider::OpenAI),
+            "KWIN-dar APIs, Quindars"
+        );
+    }
+
+    #[test]
+    fn case_sensitivity() {
+        let lexicon = store(&[("SQL", "sequel", true), ("nginx", "engine x", false)]);
+        assert_eq!(
+            lexicon.apply("sql SQL NGINX", &TtsProvider::OpenAI),
+            "sql sequel engine x"
+        );
+    }
+
+    #[test]
+    fn escapes_xml_for_edge() {
+        let lexicon = store(&[("AT&T", "A T and T", false)]);
+        lexicon
+            .entries
+            .insert(LexiconEntry {
+                word: "Quindar".to_string(),
+                respelling: None,
+                phoneme: Some("ˈkwɪn\"dɑr".to_string()),
+                alphabet: default_alphabet(),
+                case_sensitive: false,
+            })
+            .unwrap();
+        assert_eq!(
+            lexicon.apply("AT&T <3 quindar", &TtsProvider::Edge),
+            "A T and T &lt;3 <phoneme alphabet=\"ipa\" ph=\"ˈkwɪn&quot;dɑr\">quindar</phoneme>"
+        );
+    }
+
+    #[test]
+    fn rebuilds_after_entries_change() {
+        let lexicon = store(&[("Quindar", "KWIN-dar", false)]);
+        assert_eq!(lexicon.apply("Quindar", &TtsProvider::OpenAI), "KWIN-dar");
+        lexicon.entries.remove("Quindar");
+        assert_eq!(lexicon.apply("Quindar", &TtsProvider::OpenAI), "Quindar");
+    }
+}
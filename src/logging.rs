@@ -0,0 +1,154 @@
+//! Structured logging: `RUST_LOG`-style filtering, pretty/compact/JSON output and an
+//! optional rotating log file
+
+use crate::config::LoggingConfig;
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt, fmt::MakeWriter, layer::Layered, layer::SubscriberExt, reload,
+    util::SubscriberInitExt,
+};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Swaps the level filter when the configuration is reloaded
+static FILTER: OnceLock<reload::Handle<EnvFilter, Layered<Vec<BoxedLayer>, Registry>>> =
+    OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Compact,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "pretty" | "text" => Some(LogFormat::Pretty),
+            "compact" => Some(LogFormat::Compact),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl LogRotation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "minutely" => Some(LogRotation::Minutely),
+            "hourly" => Some(LogRotation::Hourly),
+            "daily" => Some(LogRotation::Daily),
+            "never" => Some(LogRotation::Never),
+            _ => None,
+        }
+    }
+
+    fn rotation(&self) -> Rotation {
+        match self {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+fn format_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
+
+/// Rolling file appender for the configured log file, pruned to `max_files`
+fn file_appender(config: &LoggingConfig, path: &Path) -> Result<RollingFileAppender, String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Log file '{}' is not a file path", path.display()))?;
+    let directory = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(config.rotation.rotation())
+        .filename_prefix(file_name.to_string_lossy());
+    if let Some(max) = config.max_files {
+        builder = builder.max_log_files(max);
+    }
+
+    builder
+        .build(directory)
+        .map_err(|e| format!("Failed to open log file {}: {}", path.display(), e))
+}
+
+/// Install the global subscriber for the configured level, format and log file.
+///
+/// Keep the returned guard alive for the life of the process - dropping it stops the
+/// background writer flushing to the log file.
+pub fn init(config: &LoggingConfig) -> Option<WorkerGuard> {
+    // The level is validated with the rest of the config, so this only guards against misuse
+    let filter = EnvFilter::try_new(&config.level).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // Colour only when a person is watching - not in JSON, files or piped output
+    let ansi = config.format != LogFormat::Json && std::io::stdout().is_terminal();
+    let mut layers = vec![format_layer(config.format, std::io::stdout, ansi)];
+    let mut guard = None;
+    let mut file_error = None;
+    if let Some(path) = &config.file {
+        match file_appender(config, path) {
+            Ok(appender) => {
+                let (writer, file_guard) = tracing_appender::non_blocking(appender);
+                layers.push(format_layer(config.format, writer, false));
+                guard = Some(file_guard);
+            }
+            Err(e) => file_error = Some(e),
+        }
+    }
+
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .init();
+
+    if let Some(e) = file_error {
+        tracing::warn!("{} - logging to stdout only", e);
+    }
+    guard
+}
+
+/// Apply a new level or filter directives to the running subscriber
+pub fn set_level(level: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| format!("Invalid log level: {}", e))?;
+    FILTER
+        .get()
+        .ok_or_else(|| "Logging is not initialized".to_string())?
+        .reload(filter)
+        .map_err(|e| format!("Failed to change log level: {}", e))
+}
@@ -1,63 +1,132 @@
+mod ack;
+mod alertmanager;
+mod chat;
+mod cli;
+mod config;
+mod dashboard;
+mod dnd;
+mod events;
+mod health;
+mod lexicon;
+mod logging;
+mod mcp;
+mod metrics;
+mod persisted;
+mod profiles;
+mod queue;
+mod reload;
+mod schedule;
+mod speech;
+mod ssml;
+mod store;
+mod templates;
+mod voices;
+mod webhook;
+mod ws;
+
 use axum::{
     Router,
-    extract::{Json, State},
+    extract::{ConnectInfo, Json, Path, State},
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::post,
+    routing::{delete, get, post},
 };
 use msedge_tts::tts::{SpeechConfig, client::connect_async};
 use notify_rust::Notification;
 use rodio::{Decoder, OutputStream, Sink, Source};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
 use std::f32::consts::PI;
 use std::io::Cursor;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
 /// TTS Provider options
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum TtsProvider {
     Edge,
+    #[serde(rename = "openai")]
     OpenAI,
 }
 
 impl TtsProvider {
-    fn from_env() -> Self {
-        match std::env::var("DEFAULT_TTS").as_deref() {
-            Ok("OPENAI") => TtsProvider::OpenAI,
-            Ok("EDGE") => TtsProvider::Edge,
-            _ => TtsProvider::Edge, // Default to Edge
+    /// The provider set by `tts.provider` / DEFAULT_TTS
+    fn configured() -> Self {
+        config::get().tts.provider.clone()
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "OPENAI" => Some(TtsProvider::OpenAI),
+            "EDGE" => Some(TtsProvider::Edge),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            TtsProvider::Edge => "EDGE",
+            TtsProvider::OpenAI => "OPENAI",
         }
     }
 }
 
 /// Tone Type options
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 enum ToneType {
-    Quindar,   // Classic NASA Quindar tones (default)
-    None,      // No tones, just voice
+    #[serde(rename = "QUINDAR")]
+    Quindar, // Classic NASA Quindar tones (default)
+    #[serde(rename = "NO-TONE")]
+    None, // No tones, just voice
+    #[serde(rename = "THREE-NOTE-CHIME")]
     ThreeNote, // Three-note audience recall chime
 }
 
 impl ToneType {
     fn from_str(s: &str) -> Self {
+        Self::parse(s).unwrap_or(ToneType::Quindar) // Default to Quindar
+    }
+
+    fn parse(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
-            "NO-TONE" | "NONE" => ToneType::None,
-            "THREE-NOTE" | "THREE-NOTE-CHIME" | "CHIME" => ToneType::ThreeNote,
-            _ => ToneType::Quindar, // Default to Quindar
+            "QUINDAR" => Some(ToneType::Quindar),
+            "NO-TONE" | "NONE" => Some(ToneType::None),
+            "THREE-NOTE" | "THREE-NOTE-CHIME" | "CHIME" => Some(ToneType::ThreeNote),
+            _ => None,
+        }
+    }
+
+    /// The tone set by `defaults.tone` / DEFAULT_TONE
+    fn configured() -> Self {
+        config::get().defaults.tone.clone()
+    }
+
+    /// Next more attention-grabbing tone, used when escalating unacknowledged alerts
+    fn escalate(&self) -> Self {
+        match self {
+            ToneType::None => ToneType::ThreeNote,
+            ToneType::ThreeNote | ToneType::Quindar => ToneType::Quindar,
         }
     }
 
-    fn from_env() -> Self {
-        match std::env::var("DEFAULT_TONE").as_deref() {
-            Ok(tone_str) => Self::from_str(tone_str),
-            _ => ToneType::Quindar, // Default to Quindar
+    fn as_str(&self) -> &str {
+        match self {
+            ToneType::Quindar => "QUINDAR",
+            ToneType::None => "NO-TONE",
+            ToneType::ThreeNote => "THREE-NOTE-CHIME",
         }
     }
 }
 
 /// Toast notification urgency levels
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum ToastUrgency {
     Info,     // General information - blue icon
     Warning,  // Warning message - yellow/orange icon
@@ -66,10 +135,31 @@ enum ToastUrgency {
 
 impl ToastUrgency {
     fn from_str(s: &str) -> Self {
+        Self::parse(s).unwrap_or(ToastUrgency::Info) // Default to Info
+    }
+
+    fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
-            "warning" | "warn" => ToastUrgency::Warning,
-            "critical" | "error" | "urgent" => ToastUrgency::Critical,
-            _ => ToastUrgency::Info, // Default to Info
+            "info" => Some(ToastUrgency::Info),
+            "warning" | "warn" => Some(ToastUrgency::Warning),
+            "critical" | "error" | "urgent" => Some(ToastUrgency::Critical),
+            _ => None,
+        }
+    }
+
+    /// Next urgency level, used when escalating unacknowledged alerts
+    fn escalate(&self) -> Self {
+        match self {
+            ToastUrgency::Info => ToastUrgency::Warning,
+            ToastUrgency::Warning | ToastUrgency::Critical => ToastUrgency::Critical,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            ToastUrgency::Info => "info",
+            ToastUrgency::Warning => "warning",
+            ToastUrgency::Critical => "critical",
         }
     }
 
@@ -90,23 +180,165 @@ impl ToastUrgency {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct PlayRequest {
+    /// What to say - or leave empty and name a `template` instead
+    #[serde(default)]
     text: String,
-    #[serde(default = "default_voice")]
-    voice: String,
+    /// `text` is SSML, e.g. `<speak>Hello <break time="500ms"/> world</speak>`
+    #[serde(default)]
+    ssml: bool,
+    /// Named template from the config, rendered with `vars` to produce the text
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    vars: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Named profile from the config supplying defaults for the fields below
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    voice: Option<String>,
     #[serde(default)]
     instructions: Option<String>,
-    #[serde(default = "default_speed")]
-    speed: f32,
-    #[serde(default = "default_volume")]
-    volume: f32,
+    #[serde(default)]
+    speed: Option<f32>,
+    #[serde(default)]
+    volume: Option<f32>,
+    /// Voice pitch shift in Hz, -100 to 100
+    #[serde(default)]
+    pitch: Option<i32>,
+    /// Voice loudness change in percent, -100 to 100 - unlike `volume`, this changes how
+    /// the voice is synthesized rather than the playback gain
+    #[serde(default)]
+    voice_volume: Option<i32>,
     #[serde(default)]
     tone: Option<String>,
     #[serde(default)]
     enable_toast: Option<bool>,
     #[serde(default)]
     toast_urgency: Option<String>,
+    #[serde(default)]
+    require_ack: Option<bool>,
+    #[serde(default)]
+    ack_timeout_secs: Option<u64>,
+    #[serde(default)]
+    ack_max_escalations: Option<u32>,
+}
+
+impl PlayRequest {
+    /// Resolve request fields against the named profile and configured defaults into a
+    /// queued transmission
+    fn into_transmission(self) -> Result<TransmissionRequest, String> {
+        let text = match &self.template {
+            Some(_) if !self.text.trim().is_empty() => {
+                return Err("Give either text or template, not both".to_string());
+            }
+            Some(name) => templates::render(name, &self.vars.clone().unwrap_or_default())?,
+            None if self.text.trim().is_empty() => {
+                return Err("Either text or template is required".to_string());
+            }
+            None => self.text.clone(),
+        };
+        if self.ssml {
+            ssml::validate(&text)?;
+        }
+        if let Some(volume) = self.volume.filter(|volume| !VOLUME_RANGE.contains(volume)) {
+            return Err(format!(
+                "volume must be between {} and {}, got {}",
+                VOLUME_RANGE.start(),
+                VOLUME_RANGE.end(),
+                volume
+            ));
+        }
+        for (name, value) in [("pitch", self.pitch), ("voice_volume", self.voice_volume)] {
+            if let Some(value) = value.filter(|value| !PROSODY_RANGE.contains(value)) {
+                return Err(format!(
+                    "{} must be between {} and {}, got {}",
+                    name,
+                    PROSODY_RANGE.start(),
+                    PROSODY_RANGE.end(),
+                    value
+                ));
+            }
+        }
+
+        // Priority: per-request > profile > configured default
+        let config = config::get();
+        let profile = match &self.profile {
+            Some(name) => config.profiles.get(name).cloned().ok_or_else(|| {
+                let available: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+                if available.is_empty() {
+                    format!("Unknown profile '{}' (no profiles are configured)", name)
+                } else {
+                    format!(
+                        "Unknown profile '{}' (available: {})",
+                        name,
+                        available.join(", ")
+                    )
+                }
+            })?,
+            None => config::ProfileConfig::default(),
+        };
+
+        // Determine tone type (from request, profile or configured default)
+        let tone_type = match self.tone.as_ref().or(profile.tone.as_ref()) {
+            Some(tone_str) => ToneType::from_str(tone_str),
+            None => ToneType::configured(),
+        };
+
+        // Determine if toast notifications should be enabled
+        let enable_toast = self
+            .enable_toast
+            .or(profile.enable_toast)
+            .unwrap_or(config.defaults.enable_toast);
+
+        // Determine toast urgency level
+        let toast_urgency = match self
+            .toast_urgency
+            .as_ref()
+            .or(profile.toast_urgency.as_ref())
+        {
+            Some(urgency_str) => ToastUrgency::from_str(urgency_str),
+            None => ToastUrgency::Info, // Default to Info
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let ack = self
+            .require_ack
+            .or(profile.require_ack)
+            .unwrap_or(false)
+            .then(|| {
+                ack::AckSettings::new(
+                    &id,
+                    self.ack_timeout_secs.or(profile.ack_timeout_secs),
+                    self.ack_max_escalations.or(profile.ack_max_escalations),
+                )
+            });
+
+        Ok(TransmissionRequest {
+            id,
+            queued_at: chrono::Utc::now(),
+            client: None,
+            text,
+            ssml: self.ssml,
+            voice: self.voice.or(profile.voice).unwrap_or_else(default_voice),
+            instructions: self.instructions.or(profile.instructions),
+            speed: self
+                .speed
+                .or(profile.speed.map(|speed| speed as f32))
+                .unwrap_or_else(default_speed),
+            volume: self
+                .volume
+                .or(profile.volume.map(|volume| volume as f32))
+                .unwrap_or_else(default_volume),
+            pitch: self.pitch.or(profile.pitch).unwrap_or(0),
+            voice_volume: self.voice_volume.or(profile.voice_volume).unwrap_or(0),
+            tone_type,
+            enable_toast,
+            toast_urgency,
+            ack,
+        })
+    }
 }
 
 fn default_voice() -> String {
@@ -117,25 +349,86 @@ fn default_speed() -> f32 {
     1.0
 }
 
+/// Accepted `volume` gain multipliers
+const VOLUME_RANGE: std::ops::RangeInclusive<f32> = 0.1..=5.0;
+
+/// Accepted `pitch` (Hz) and `voice_volume` (percent) shifts
+const PROSODY_RANGE: std::ops::RangeInclusive<i32> = -100..=100;
+
 fn default_volume() -> f32 {
     2.0 // Default to 2x volume to match Quindar tone loudness
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct TransmissionRequest {
+    id: String,
+    queued_at: chrono::DateTime<chrono::Utc>,
+    /// Who asked for this transmission (X-Client-Name, User-Agent or peer address)
+    #[serde(default)]
+    client: Option<String>,
     text: String,
+    /// `text` is SSML, translated for providers that don't take it
+    #[serde(default)]
+    ssml: bool,
     voice: String,
     instructions: Option<String>,
     speed: f32,
     volume: f32,
+    /// Voice pitch shift in Hz
+    #[serde(default)]
+    pitch: i32,
+    /// Voice loudness change in percent
+    #[serde(default)]
+    voice_volume: i32,
     tone_type: ToneType,
     enable_toast: bool,
     toast_urgency: ToastUrgency,
+    /// Present when a human must acknowledge this transmission
+    #[serde(default)]
+    ack: Option<ack::AckSettings>,
 }
 
 #[derive(Clone)]
 struct AppState {
     tx: mpsc::UnboundedSender<TransmissionRequest>,
+    schedules: Arc<schedule::ScheduleStore>,
+    store: Arc<store::Store>,
+    acks: Arc<ack::AckTracker>,
+    queue: Arc<queue::QueueTracker>,
+    webhooks: Arc<webhook::WebhookStore>,
+    /// Pronunciations applied to the text before synthesis
+    lexicon: Arc<lexicon::LexiconStore>,
+    events: Arc<events::EventBus>,
+    /// Do Not Disturb - when on, only critical transmissions play
+    dnd: tokio::sync::watch::Sender<bool>,
+    /// The --config path, re-read on reload
+    config_path: Option<std::path::PathBuf>,
+}
+
+impl AppState {
+    /// Persist a transmission and hand it to the queue processor, returning its ID
+    fn enqueue(&self, transmission: TransmissionRequest) -> Result<String, String> {
+        let id = transmission.id.clone();
+
+        // A persistence failure shouldn't lose the alert - it just won't survive a restart
+        if let Err(e) = self.store.enqueue(&transmission) {
+            warn!("{}", e);
+        }
+        self.acks.register(&transmission);
+        self.queue.push(&transmission);
+        metrics::METRICS.transmission_queued(
+            TtsProvider::configured().as_str(),
+            transmission.tone_type.as_str(),
+        );
+        self.events.emit(events::EventKind::Queued, &transmission);
+
+        if let Err(e) = self.tx.send(transmission) {
+            self.store.remove(&id);
+            return Err(format!("Failed to enqueue transmission: {}", e));
+        }
+
+        Ok(id)
+    }
 }
 
 /// Generate Quindar tone samples
@@ -243,33 +536,42 @@ fn generate_three_note_chime() -> Vec<f32> {
 
 /// Check if running in headless mode (no audio output)
 fn is_headless_mode() -> bool {
-    std::env::var("HEADLESS_MODE")
-        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
-        .unwrap_or(false)
+    config::get().server.headless
 }
 
-/// Play tones and audio based on tone type
+/// Play tones and audio based on tone type. `first` is the first chunk of voice audio;
+/// the rest arrive on `chunks` as they're synthesized and join the same sink.
 fn play_tones_and_audio(
-    audio_bytes: Vec<u8>,
+    first: Vec<u8>,
+    mut chunks: mpsc::UnboundedReceiver<Result<Vec<u8>, String>>,
     volume: f32,
     tone_type: ToneType,
+    cancel: &AtomicBool,
 ) -> Result<(), String> {
     // Check for headless mode (WSL, headless servers, testing)
     if is_headless_mode() {
-        println!("Headless mode: Skipping audio playback (TTS generated successfully)");
+        // Still wait for every chunk, so a failed one fails the transmission
+        while let Some(chunk) = chunks.blocking_recv() {
+            chunk?;
+        }
+        info!("Headless mode: skipping audio playback (TTS generated successfully)");
         return Ok(());
     }
 
-    let (_stream, stream_handle) = OutputStream::try_default()
-        .map_err(|e| format!("Failed to create output stream: {}", e))?;
-    let sink =
-        Sink::try_new(&stream_handle).map_err(|e| format!("Failed to create sink: {}", e))?;
+    let (_stream, stream_handle) = OutputStream::try_default().map_err(|e| {
+        metrics::METRICS.audio_device_error();
+        format!("Failed to create output stream: {}", e)
+    })?;
+    let sink = Sink::try_new(&stream_handle).map_err(|e| {
+        metrics::METRICS.audio_device_error();
+        format!("Failed to create sink: {}", e)
+    })?;
 
     let sample_rate = 48000;
 
     match tone_type {
         ToneType::Quindar => {
-            println!("Playing opening Quindar tone...");
+            debug!("Playing opening Quindar tone");
 
             // Opening Quindar tone (500ms)
             let opening_tone_samples = generate_quindar_tone_samples(500);
@@ -281,7 +583,7 @@ fn play_tones_and_audio(
             sink.append(opening_tone_source);
         }
         ToneType::ThreeNote => {
-            println!("Playing three-note audience recall chime...");
+            debug!("Playing three-note audience recall chime");
 
             // Three-note chime
             let chime_samples = generate_three_note_chime();
@@ -293,25 +595,40 @@ fn play_tones_and_audio(
             sink.append(chime_source);
         }
         ToneType::None => {
-            println!("No tone - playing voice only...");
+            debug!("No tone - playing voice only");
             // No opening tone, just play the voice
         }
     }
 
-    println!("Playing voice transmission (volume: {:.1}x)...", volume);
+    info!(volume, "Playing voice transmission");
 
     // TTS audio with volume boost
-    let cursor = Cursor::new(audio_bytes);
-    let source = Decoder::new(cursor).map_err(|e| format!("Failed to decode audio: {}", e))?;
-
-    // Apply volume gain
-    let amplified_source = source.amplify(volume);
-    sink.append(amplified_source);
+    append_voice(&sink, first, volume)?;
+
+    // Later chunks, appended as they arrive so they play straight after the one before
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            info!("Playback stopped (transmission cancelled)");
+            sink.stop();
+            return Ok(());
+        }
+        match chunks.try_recv() {
+            Ok(Ok(bytes)) => append_voice(&sink, bytes, volume)?,
+            Ok(Err(e)) => {
+                sink.stop();
+                return Err(e);
+            }
+            Err(mpsc::error::TryRecvError::Empty) => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            Err(mpsc::error::TryRecvError::Disconnected) => break,
+        }
+    }
 
     // Closing tone (only for Quindar and ThreeNote)
     match tone_type {
         ToneType::Quindar => {
-            println!("Playing closing Quindar tone...");
+            debug!("Playing closing Quindar tone");
 
             // Closing Quindar tone (shorter - 250ms)
             let closing_tone_samples = generate_quindar_tone_samples(250);
@@ -323,7 +640,7 @@ fn play_tones_and_audio(
             sink.append(closing_tone_source);
         }
         ToneType::ThreeNote => {
-            println!("Playing closing chime...");
+            debug!("Playing closing chime");
 
             // Shorter chime for closing (single note, like a bell)
             let closing_freq = 783.99; // G5 - final note of the chime
@@ -352,11 +669,27 @@ fn play_tones_and_audio(
         }
     }
 
-    sink.sleep_until_end();
+    // Poll rather than sleep_until_end so a cancelled transmission stops immediately
+    while !sink.empty() {
+        if cancel.load(Ordering::Relaxed) {
+            info!("Playback stopped (transmission cancelled)");
+            sink.stop();
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
 
     Ok(())
 }
 
+/// Decode one chunk of TTS audio and queue it on the sink with volume gain
+fn append_voice(sink: &Sink, audio_bytes: Vec<u8>, volume: f32) -> Result<(), String> {
+    let cursor = Cursor::new(audio_bytes);
+    let source = Decoder::new(cursor).map_err(|e| format!("Failed to decode audio: {}", e))?;
+    sink.append(source.amplify(volume));
+    Ok(())
+}
+
 /// Custom audio source for samples
 struct AudioSource {
     samples: Vec<f32>,
@@ -447,24 +780,14 @@ async fn get_openai_tts(
     Ok(audio_bytes.to_vec())
 }
 
-/// Get TTS from Edge TTS using native Rust client
-async fn get_edge_tts(text: &str, voice: &str, speed: f32) -> Result<Vec<u8>, String> {
-    // Map speed to edge-tts rate format (percentage)
-    // speed 0.25 -> -75%, speed 1.0 -> +0%, speed 4.0 -> +300%
-    let rate_percent = ((speed - 1.0) * 100.0).round() as i32;
-    let rate_str = if rate_percent >= 0 {
-        format!("+{}%", rate_percent)
-    } else {
-        format!("{}%", rate_percent)
-    };
-
-    // Get edge voice (use env var or default)
-    let edge_voice =
-        std::env::var("EDGE_VOICE").unwrap_or_else(|_| "en-US-AndrewNeural".to_string());
+/// Map a requested voice to an Edge TTS voice name
+fn resolve_edge_voice(voice: &str) -> String {
+    // Get the configured edge voice
+    let edge_voice = config::get().tts.edge_voice.clone();
 
     // Use edge voice if no specific voice mapping needed
     // In the future, we could map OpenAI voice names to Edge voices
-    let final_voice = if voice == "alloy"
+    if voice == "alloy"
         || voice == "echo"
         || voice == "fable"
         || voice == "onyx"
@@ -476,12 +799,30 @@ async fn get_edge_tts(text: &str, voice: &str, speed: f32) -> Result<Vec<u8>, St
     } else {
         // Use provided voice name (might be an edge-tts voice)
         voice.to_string()
+    }
+}
+
+/// Get TTS from Edge TTS using native Rust client. `text` goes into the SSML document as
+/// is, so it must already be escaped (see `lexicon::escape_xml`).
+async fn get_edge_tts(
+    text: &str,
+    voice: &str,
+    speed: f32,
+    pitch: i32,
+    volume: i32,
+) -> Result<Vec<u8>, String> {
+    // Map speed to edge-tts rate format (percentage)
+    // speed 0.25 -> -75%, speed 1.0 -> +0%, speed 4.0 -> +300%
+    let rate_percent = ((speed - 1.0) * 100.0).round() as i32;
+    let rate_str = if rate_percent >= 0 {
+        format!("+{}%", rate_percent)
+    } else {
+        format!("{}%", rate_percent)
     };
 
-    println!(
-        "Calling Edge TTS API with voice: {}, rate: {}",
-        final_voice, rate_str
-    );
+    let final_voice = resolve_edge_voice(voice);
+
+    debug!(voice = %final_voice, rate = %rate_str, pitch, volume, "Calling Edge TTS API");
 
     // Retry logic for Edge TTS - first request after startup sometimes fails with WebSocket error
     let max_retries = 3;
@@ -495,10 +836,8 @@ async fn get_edge_tts(text: &str, voice: &str, speed: f32) -> Result<Vec<u8>, St
             Err(e) => {
                 last_error = format!("Failed to connect to Edge TTS: {}", e);
                 if attempt < max_retries {
-                    println!(
-                        "Connection attempt {} failed, retrying... ({})",
-                        attempt, last_error
-                    );
+                    warn!(attempt, error = %last_error, "Edge TTS connection failed, retrying");
+                    metrics::METRICS.tts_retried(TtsProvider::Edge.as_str());
                     tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
                     continue;
                 }
@@ -511,26 +850,24 @@ async fn get_edge_tts(text: &str, voice: &str, speed: f32) -> Result<Vec<u8>, St
         let config = SpeechConfig {
             voice_name: final_voice.clone(),
             audio_format: "audio-24khz-48kbitrate-mono-mp3".to_string(),
-            pitch: 0,           // Normal pitch
+            pitch,              // Hz relative to the voice's default
             rate: rate_percent, // Already calculated as i32 percentage
-            volume: 0,          // Normal volume
+            volume,             // Percent relative to the voice's default
         };
 
         // Synthesize speech
         match client.synthesize(text, &config).await {
             Ok(audio_result) => {
                 if attempt > 1 {
-                    println!("✓ Connection succeeded on attempt {}", attempt);
+                    info!(attempt, "Edge TTS connection succeeded after retry");
                 }
                 return Ok(audio_result.audio_bytes);
             }
             Err(e) => {
                 last_error = format!("Failed to synthesize speech: {}", e);
                 if attempt < max_retries {
-                    println!(
-                        "Synthesis attempt {} failed, retrying... ({})",
-                        attempt, last_error
-                    );
+                    warn!(attempt, error = %last_error, "Edge TTS synthesis failed, retrying");
+                    metrics::METRICS.tts_retried(TtsProvider::Edge.as_str());
                     tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
                     continue;
                 }
@@ -552,274 +889,820 @@ fn show_toast_notification(text: &str, urgency: &ToastUrgency) {
         .show();
 
     match result {
-        Ok(_) => println!("Toast notification shown: {}", text),
-        Err(e) => eprintln!("Failed to show toast notification: {}", e),
+        Ok(_) => info!(urgency = urgency.as_str(), "Toast notification shown"),
+        Err(e) => warn!("Failed to show toast notification: {}", e),
     }
 }
 
-/// Process a single transmission (called by queue processor)
-async fn process_transmission(req: TransmissionRequest) {
-    println!(
-        "\n=== Processing transmission: {} (voice: {}) ===",
-        req.text, req.voice
+/// Process a single transmission (called by queue processor) and record it in history
+async fn process_transmission(req: TransmissionRequest, state: &AppState, cancel: Arc<AtomicBool>) {
+    let started_at = chrono::Utc::now();
+
+    // Determine TTS provider
+    let tts_provider = TtsProvider::configured();
+
+    // Everything logged while this transmission is processed carries its ID, provider and voice
+    let span = info_span!(
+        "transmission",
+        id = %req.id,
+        provider = tts_provider.as_str(),
+        voice = %req.voice,
+        client = req.client.as_deref().unwrap_or("unknown"),
+    );
+    info!(
+        parent: &span,
+        text = %req.text,
+        queued_ms = (started_at - req.queued_at).num_milliseconds(),
+        "Processing transmission"
+    );
+
+    // Speak a cleaned-up version; the toast and history keep the original text. SSML is
+    // already written for speech.
+    let spoken = if req.ssml {
+        req.text.clone()
+    } else {
+        speech::normalize(&req.text, req.enable_toast)
+    };
+    if spoken != req.text {
+        debug!(parent: &span, spoken = %spoken, "Normalized text for speech");
+    }
+
+    let result = transmit(&req, &spoken, tts_provider.clone(), state, cancel.clone())
+        .instrument(span.clone())
+        .await;
+    let duration_ms = (chrono::Utc::now() - started_at).num_milliseconds();
+    let (status, kind) = if cancel.load(Ordering::Relaxed) {
+        info!(parent: &span, duration_ms, "Transmission cancelled");
+        ("cancelled", events::EventKind::Cancelled)
+    } else {
+        match &result {
+            Ok(()) => {
+                info!(parent: &span, duration_ms, "Transmission complete");
+                ("completed", events::EventKind::Completed)
+            }
+            Err(e) => {
+                error!(parent: &span, duration_ms, error = %e, "Transmission failed");
+                ("failed", events::EventKind::Failed)
+            }
+        }
+    };
+    state
+        .events
+        .finish(kind, &req, started_at, result.clone().err());
+    metrics::METRICS.transmission_finished(tts_provider.as_str(), req.tone_type.as_str(), status);
+
+    state.store.record_history(
+        &req,
+        &tts_provider,
+        started_at,
+        chrono::Utc::now(),
+        status,
+        result.err(),
     );
+}
 
+/// Synthesize `spoken` (or reuse cached audio) and play a transmission
+async fn transmit(
+    req: &TransmissionRequest,
+    spoken: &str,
+    tts_provider: TtsProvider,
+    state: &AppState,
+    cancel: Arc<AtomicBool>,
+) -> Result<(), String> {
     // Show toast notification if enabled
     if req.enable_toast {
-        show_toast_notification(&req.text, &req.toast_urgency);
+        let text = if req.ssml {
+            ssml::to_plain(&req.text)
+                .map(|plain| plain.text)
+                .unwrap_or_else(|_| req.text.clone())
+        } else {
+            req.text.clone()
+        };
+        match &req.ack {
+            Some(ack) => show_toast_notification(
+                &format!("{}\nAcknowledge: {}", text, state.acks.ack_url(&ack.id)),
+                &req.toast_urgency,
+            ),
+            None => show_toast_notification(&text, &req.toast_urgency),
+        }
     }
 
-    // Determine TTS provider
-    let tts_provider = TtsProvider::from_env();
-
     // Validate API key if using OpenAI
-    if tts_provider == TtsProvider::OpenAI && std::env::var("OPENAI_API_KEY").is_err() {
-        eprintln!("Error: OPENAI_API_KEY not set but DEFAULT_TTS=OPENAI");
-        return;
+    if tts_provider == TtsProvider::OpenAI && config::get().tts.openai_api_key.is_none() {
+        return Err("Error: OPENAI_API_KEY not set but DEFAULT_TTS=OPENAI".to_string());
     }
 
-    // Start requesting TTS immediately (async)
-    let mut log_msg = match tts_provider {
-        TtsProvider::OpenAI => format!("Requesting TTS from OpenAI with voice '{}'", req.voice),
-        TtsProvider::Edge => format!("Requesting TTS from Edge TTS with voice '{}'", req.voice),
+    // SSML can't be cut at an arbitrary sentence without breaking its markup
+    let chunks = if req.ssml {
+        vec![spoken.to_string()]
+    } else {
+        speech::chunks(spoken, config::get().tts.chunk_chars)
     };
-
-    if req.speed != 1.0 {
-        log_msg.push_str(&format!(", speed: {}", req.speed));
-    }
-    if req.volume != 2.0 {
-        log_msg.push_str(&format!(", volume: {:.1}x", req.volume));
+    let inputs = chunks
+        .iter()
+        .map(|chunk| tts_input(req, chunk, &tts_provider, &state.lexicon))
+        .collect::<Result<Vec<_>, _>>()?;
+    if inputs.len() > 1 {
+        debug!(chunks = inputs.len(), "Synthesizing in chunks");
     }
-    if let Some(ref instr) = req.instructions {
-        log_msg.push_str(&format!(", instructions: '{}'", instr));
-    }
-    println!("{}...", log_msg);
 
-    let text = req.text.clone();
-    let voice = req.voice.clone();
-    let instructions = req.instructions.clone();
-    let speed = req.speed;
-
-    let tts_task = tokio::spawn(async move {
-        match tts_provider {
-            TtsProvider::OpenAI => {
-                let api_key = std::env::var("OPENAI_API_KEY").unwrap();
-                get_openai_tts(&text, &voice, instructions.as_deref(), speed, &api_key).await
-            }
-            TtsProvider::Edge => {
-                // Edge TTS doesn't support instructions parameter
-                if instructions.is_some() {
-                    println!(
-                        "Note: Edge TTS does not support instructions parameter (OpenAI only)"
-                    );
+    // Synthesize every chunk concurrently, handing them to playback in order
+    let synthesis_started = std::time::Instant::now();
+    let announced = Arc::new(AtomicBool::new(false));
+    let permits = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_CHUNKS));
+    let shared_req = Arc::new(req.clone());
+    let tasks: Vec<_> = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let req = shared_req.clone();
+            let store = state.store.clone();
+            let bus = state.events.clone();
+            let announced = announced.clone();
+            let permits = permits.clone();
+            let tts_provider = tts_provider.clone();
+            tokio::spawn(
+                async move {
+                    let _permit = permits.acquire_owned().await.map_err(|e| e.to_string())?;
+                    synthesize_chunk(&req, index, &input, tts_provider, &store, || {
+                        if !announced.swap(true, Ordering::Relaxed) {
+                            bus.emit(events::EventKind::Synthesizing, &req);
+                        }
+                    })
+                    .await
                 }
-                get_edge_tts(&text, &voice, speed).await
+                .in_current_span(),
+            )
+        })
+        .collect();
+
+    let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel::<Result<Vec<u8>, String>>();
+    tokio::spawn(async move {
+        for task in tasks {
+            let result = task
+                .await
+                .unwrap_or_else(|e| Err(format!("TTS task failed: {}", e)));
+            if chunk_tx.send(result).is_err() {
+                break;
             }
         }
     });
 
-    // Wait for TTS to complete buffering (no pre-transmission audio)
-    let audio_bytes = match tts_task.await {
-        Ok(Ok(bytes)) => {
-            println!("Voice buffered successfully!");
-            bytes
-        }
-        Ok(Err(e)) => {
-            eprintln!("Error getting TTS: {}", e);
-            return;
-        }
-        Err(e) => {
-            eprintln!("Task error: {}", e);
-            return;
-        }
+    let first = match chunk_rx.recv().await {
+        Some(result) => result?,
+        None => return Err("TTS produced no audio".to_string()),
     };
+    debug!(
+        first_audio_ms = synthesis_started.elapsed().as_millis() as u64,
+        "First chunk ready"
+    );
+
+    if cancel.load(Ordering::Relaxed) {
+        return Err("Transmission cancelled before playback".to_string());
+    }
 
     // Now play tones and audio based on tone type
+    state.events.emit(events::EventKind::Playing, req);
     let volume = req.volume;
     let tone_type = req.tone_type.clone();
-    if let Err(e) = tokio::task::spawn_blocking(move || {
-        if let Err(e) = play_tones_and_audio(audio_bytes, volume, tone_type) {
-            eprintln!("Error playing audio: {}", e);
-        }
+    let playback_started = std::time::Instant::now();
+    let span = tracing::Span::current();
+    match tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        play_tones_and_audio(first, chunk_rx, volume, tone_type, &cancel)
     })
     .await
     {
-        eprintln!("Audio playback task failed: {}", e);
+        Ok(Ok(())) => {
+            metrics::METRICS.playback_finished(playback_started.elapsed());
+            debug!(
+                playback_ms = playback_started.elapsed().as_millis() as u64,
+                "Playback finished"
+            );
+            Ok(())
+        }
+        Ok(Err(e)) => Err(format!("Error playing audio: {}", e)),
+        Err(e) => Err(format!("Audio playback task failed: {}", e)),
     }
+}
 
-    println!("Transmission complete!\n");
+/// Chunks of one transmission synthesized at the same time, to stay within provider
+/// rate limits
+const MAX_CONCURRENT_CHUNKS: usize = 3;
+
+/// Synthesize one chunk, or reuse cached audio for identical text, voice and speed.
+/// `on_miss` runs before a chunk goes to the provider.
+async fn synthesize_chunk(
+    req: &TransmissionRequest,
+    index: usize,
+    input: &TtsInput,
+    tts_provider: TtsProvider,
+    store: &store::Store,
+    on_miss: impl FnOnce(),
+) -> Result<Vec<u8>, String> {
+    let cache_key = audio_cache_key(req, input, &tts_provider);
+    if let Some(bytes) = store.cached_audio(&cache_key) {
+        info!(chunk = index, bytes = bytes.len(), "Using cached audio");
+        metrics::METRICS.audio_cache(true);
+        return Ok(bytes);
+    }
+
+    metrics::METRICS.audio_cache(false);
+    on_miss();
+    let synthesis_started = std::time::Instant::now();
+    let bytes = synthesize(req, input, tts_provider.clone())
+        .await
+        .inspect_err(|_| metrics::METRICS.tts_failed(tts_provider.as_str()))?;
+    metrics::METRICS.tts_succeeded(tts_provider.as_str(), synthesis_started.elapsed());
+    info!(
+        chunk = index,
+        bytes = bytes.len(),
+        synthesis_ms = synthesis_started.elapsed().as_millis() as u64,
+        "Voice buffered"
+    );
+    store.cache_audio(&cache_key, &bytes);
+    Ok(bytes)
 }
 
-/// Background task that processes the transmission queue
-async fn transmission_queue_processor(mut rx: mpsc::UnboundedReceiver<TransmissionRequest>) {
-    println!("Transmission queue processor started");
+/// What the TTS provider is asked to say, and how
+struct TtsInput {
+    /// SSML fragment for Edge, plain text for OpenAI
+    text: String,
+    speed: f32,
+    instructions: Option<String>,
+    /// Edge only - OpenAI gets them as instructions
+    pitch: i32,
+    voice_volume: i32,
+}
 
-    while let Some(req) = rx.recv().await {
-        process_transmission(req).await;
+/// Apply the pronunciation lexicon and, for SSML requests, translate the markup for
+/// the provider: Edge takes it as is, OpenAI gets plain text with prosody folded into
+/// speed and instructions
+fn tts_input(
+    req: &TransmissionRequest,
+    spoken: &str,
+    tts_provider: &TtsProvider,
+    lexicon: &lexicon::LexiconStore,
+) -> Result<TtsInput, String> {
+    let mut input = TtsInput {
+        text: String::new(),
+        speed: req.speed,
+        instructions: req.instructions.clone(),
+        pitch: req.pitch,
+        voice_volume: req.voice_volume,
+    };
+    let mut hints = Vec::new();
+
+    match (req.ssml, tts_provider) {
+        (false, _) => input.text = lexicon.apply(spoken, tts_provider),
+        (true, TtsProvider::Edge) => input.text = ssml::edge_fragment(spoken, lexicon)?,
+        (true, TtsProvider::OpenAI) => {
+            let plain = ssml::to_plain(spoken)?;
+            input.text = lexicon.apply(&plain.text, tts_provider);
+            if let Some(rate) = plain.rate {
+                input.speed = (req.speed * rate).clamp(0.25, 4.0);
+            }
+            hints = plain.hints;
+        }
+    }
+
+    // OpenAI has no pitch or volume settings, but follows delivery instructions
+    if *tts_provider == TtsProvider::OpenAI {
+        hints.extend(prosody_hint(
+            req.pitch,
+            "Speak with a {}higher pitch.",
+            "Speak with a {}lower pitch.",
+        ));
+        hints.extend(prosody_hint(
+            req.voice_volume,
+            "Speak {}louder.",
+            "Speak {}more softly.",
+        ));
+        input.pitch = 0;
+        input.voice_volume = 0;
+    }
+    if !hints.is_empty() {
+        let hints = hints.join(" ");
+        input.instructions = Some(match input.instructions {
+            Some(instructions) => format!("{} {}", instructions, hints),
+            None => hints,
+        });
     }
 
-    println!("Transmission queue processor stopped");
+    Ok(input)
 }
 
-/// Load .env file from executable directory or current directory
-#[allow(clippy::collapsible_if)]
-fn load_env_file() {
-    use std::path::PathBuf;
+/// An instruction like "Speak slightly louder." for a pitch or voice volume shift; `{}`
+/// in the phrases marks where "slightly " goes for small shifts
+fn prosody_hint(shift: i32, raised: &str, lowered: &str) -> Option<String> {
+    let phrase = match shift {
+        0 => return None,
+        shift if shift > 0 => raised,
+        _ => lowered,
+    };
+    let degree = if shift.abs() <= 20 { "slightly " } else { "" };
+    Some(phrase.replace("{}", degree))
+}
 
-    let mut loaded = false;
-    let mut env_path: Option<PathBuf> = None;
+/// Cache key covering everything that affects the synthesized audio
+fn audio_cache_key(
+    req: &TransmissionRequest,
+    input: &TtsInput,
+    tts_provider: &TtsProvider,
+) -> String {
+    let voice = match tts_provider {
+        TtsProvider::Edge => resolve_edge_voice(&req.voice),
+        TtsProvider::OpenAI => req.voice.clone(),
+    };
 
-    // Try 1: Load from executable's directory
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let exe_env = exe_dir.join(".env");
-            if exe_env.exists() {
-                match dotenv::from_path(&exe_env) {
-                    Ok(_) => {
-                        loaded = true;
-                        env_path = Some(exe_env);
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "Warning: Found .env at {:?} but failed to load: {}",
-                            exe_env, e
-                        );
+    let mut hasher = Sha256::new();
+    for part in [
+        tts_provider.as_str(),
+        &voice,
+        &input.speed.to_string(),
+        &input.pitch.to_string(),
+        &input.voice_volume.to_string(),
+        input.instructions.as_deref().unwrap_or(""),
+        &input.text,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Request TTS audio for a transmission's prepared input from the configured provider
+async fn synthesize(
+    req: &TransmissionRequest,
+    input: &TtsInput,
+    tts_provider: TtsProvider,
+) -> Result<Vec<u8>, String> {
+    // Start requesting TTS immediately (async)
+    info!(
+        speed = input.speed,
+        pitch = input.pitch,
+        voice_volume = input.voice_volume,
+        instructions = input.instructions.as_deref(),
+        "Requesting TTS"
+    );
+
+    debug!(input = %input.text, "TTS input");
+
+    let text = input.text.clone();
+    let voice = req.voice.clone();
+    let instructions = input.instructions.clone();
+    let speed = input.speed;
+    let (pitch, voice_volume) = (input.pitch, input.voice_volume);
+
+    let tts_task = tokio::spawn(
+        async move {
+            match tts_provider {
+                TtsProvider::OpenAI => {
+                    let api_key = config::get().tts.openai_api_key.clone().unwrap_or_default();
+                    get_openai_tts(&text, &voice, instructions.as_deref(), speed, &api_key).await
+                }
+                TtsProvider::Edge => {
+                    // Edge TTS doesn't support instructions parameter
+                    if instructions.is_some() {
+                        warn!("Edge TTS does not support the instructions parameter (OpenAI only)");
                     }
+                    get_edge_tts(&text, &voice, speed, pitch, voice_volume).await
                 }
             }
         }
+        .in_current_span(),
+    );
+
+    // Wait for TTS to complete buffering (no pre-transmission audio)
+    match tts_task.await {
+        Ok(Ok(bytes)) => Ok(bytes),
+        Ok(Err(e)) => Err(format!("Error getting TTS: {}", e)),
+        Err(e) => Err(format!("Task error: {}", e)),
     }
+}
 
-    // Try 2: Load from current directory (if not already loaded)
-    if !loaded {
-        match dotenv::dotenv() {
-            Ok(path) => {
-                env_path = Some(path);
-            }
-            Err(_) => {
-                // No .env file found in current directory either
-            }
+/// Background task that processes the transmission queue
+async fn transmission_queue_processor(
+    mut rx: mpsc::UnboundedReceiver<TransmissionRequest>,
+    state: Arc<AppState>,
+) {
+    info!("Transmission queue processor started");
+
+    let mut dnd = state.dnd.subscribe();
+    let mut held: VecDeque<TransmissionRequest> = VecDeque::new();
+
+    loop {
+        // Release transmissions held during Do Not Disturb once it's switched off
+        let released = if *dnd.borrow() {
+            None
+        } else {
+            held.pop_front()
+        };
+        let req = match released {
+            Some(req) => req,
+            None => tokio::select! {
+                received = rx.recv() => match received {
+                    Some(req) => req,
+                    None => break,
+                },
+                changed = dnd.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            },
+        };
+        let id = req.id.clone();
+
+        // Critical alerts break through Do Not Disturb; everything else waits
+        if *dnd.borrow() && req.toast_urgency != ToastUrgency::Critical {
+            info!(id = %id, "Holding transmission - do not disturb is on");
+            held.push_back(req);
+            continue;
+        }
+
+        let Some(cancel) = state.queue.start(&req) else {
+            info!(id = %id, "Skipping transmission - cancelled");
+            metrics::METRICS.transmission_finished(
+                TtsProvider::configured().as_str(),
+                req.tone_type.as_str(),
+                "cancelled",
+            );
+            continue;
+        };
+
+        // Escalations still queued when the human acknowledged are no longer needed
+        if req
+            .ack
+            .as_ref()
+            .is_some_and(|ack| !state.acks.is_pending(&ack.id))
+        {
+            info!(id = %id, "Skipping transmission - already acknowledged");
+            metrics::METRICS.transmission_finished(
+                TtsProvider::configured().as_str(),
+                req.tone_type.as_str(),
+                "cancelled",
+            );
+            state.events.emit(events::EventKind::Cancelled, &req);
+            state.queue.finish();
+            state.store.remove(&id);
+            continue;
+        }
+
+        state.store.mark_in_flight(&id);
+        process_transmission(req.clone(), &state, cancel.clone()).await;
+        state.queue.finish();
+        state.store.remove(&id);
+
+        if !cancel.load(Ordering::Relaxed) {
+            ack::await_ack(state.clone(), req);
         }
     }
 
-    // Log the result
-    if let Some(path) = env_path {
-        println!("Loaded configuration from: {}", path.display());
-    } else {
-        println!("No .env file found - using default configuration and environment variables");
+    info!("Transmission queue processor stopped");
+}
+
+/// Load .env file from executable directory or current directory.
+///
+/// Runs before logging is set up (the .env file may configure it), so the file loaded
+/// and any warning are returned for the caller to log.
+fn load_env_file() -> (Option<std::path::PathBuf>, Option<String>) {
+    match config::read_env_file() {
+        Ok((path, vars)) => {
+            config::set_env_file(vars);
+            (path, None)
+        }
+        Err(warning) => (None, Some(warning)),
+    }
+}
+
+/// Identify the requesting client for history: X-Client-Name, then User-Agent, then peer address
+fn client_name(headers: &HeaderMap, addr: &SocketAddr) -> String {
+    ["x-client-name", "user-agent"]
+        .iter()
+        .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()))
+        .map(|name| format!("{} ({})", name, addr.ip()))
+        .unwrap_or_else(|| addr.ip().to_string())
+}
+
+/// Check the admin token, when one is set, for a route that changes server state
+fn authorize_admin(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    match &config::get().server.admin_token {
+        Some(token) if !alertmanager::authorized(headers, token) => Err((
+            StatusCode::UNAUTHORIZED,
+            "Error: Invalid or missing bearer token".to_string(),
+        )),
+        _ => Ok(()),
     }
 }
 
 /// API handler to enqueue transmission requests
 async fn play_tone_handler(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<PlayRequest>,
 ) -> impl IntoResponse {
-    let mut log_msg = format!(
-        "Received request, adding to queue: {} (voice: {})",
-        payload.text, payload.voice
+    let client = client_name(&headers, &addr);
+    info!(
+        client = %client,
+        voice = payload.voice.as_deref(),
+        profile = payload.profile.as_deref(),
+        template = payload.template.as_deref(),
+        "Received request, adding to queue: {}",
+        payload.text
     );
-    if payload.speed != 1.0 {
-        log_msg.push_str(&format!(", speed: {}", payload.speed));
-    }
-    if payload.volume != 2.0 {
-        log_msg.push_str(&format!(", volume: {:.1}x", payload.volume));
-    }
-    if let Some(ref instr) = payload.instructions {
-        log_msg.push_str(&format!(", instructions: '{}'", instr));
-    }
-    println!("{}", log_msg);
 
-    // Determine tone type (from request or environment default)
-    let tone_type = match &payload.tone {
-        Some(tone_str) => ToneType::from_str(tone_str),
-        None => ToneType::from_env(),
+    let mut transmission = match payload.into_transmission() {
+        Ok(transmission) => transmission,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Error: {}", e)).into_response(),
     };
+    transmission.client = Some(client);
 
-    // Determine if toast notifications should be enabled
-    // Priority: per-request > environment variable > false (default)
-    let enable_toast = match payload.enable_toast {
-        Some(val) => val,
-        None => std::env::var("ENABLE_TOAST_NOTIFICATIONS")
-            .unwrap_or_else(|_| "false".to_string())
-            .to_lowercase() == "true",
-    };
+    queued_response(&state, transmission)
+}
+
+/// Enqueue a transmission and report its ID (and ack URL, if required) in response headers
+fn queued_response(
+    state: &AppState,
+    transmission: TransmissionRequest,
+) -> axum::response::Response {
+    let ack_url = transmission
+        .ack
+        .as_ref()
+        .map(|ack| state.acks.ack_url(&ack.id));
+
+    match state.enqueue(transmission) {
+        Ok(id) => {
+            let mut headers = HeaderMap::new();
+            if let Ok(value) = id.parse() {
+                headers.insert("X-Transmission-Id", value);
+            }
+            if let Some(value) = ack_url.and_then(|url| url.parse().ok()) {
+                headers.insert("X-Ack-Url", value);
+            }
+            (
+                StatusCode::OK,
+                headers,
+                "Transmission queued successfully!".to_string(),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("{}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error: Failed to queue transmission".to_string(),
+            )
+                .into_response()
+        }
+    }
+}
 
-    // Determine toast urgency level
-    let toast_urgency = match &payload.toast_urgency {
-        Some(urgency_str) => ToastUrgency::from_str(urgency_str),
-        None => ToastUrgency::Info, // Default to Info
+/// Re-enqueue a past transmission from history under a new ID
+fn replay(
+    state: &AppState,
+    original: Option<TransmissionRequest>,
+    client: String,
+) -> axum::response::Response {
+    let Some(original) = original else {
+        return (
+            StatusCode::NOT_FOUND,
+            "Error: Transmission not found in history".to_string(),
+        )
+            .into_response();
     };
 
+    info!(original = %original.id, "Replaying transmission: {}", original.text);
+
+    // A replay is for hearing the message again, not a new alert to acknowledge
     let transmission = TransmissionRequest {
-        text: payload.text,
-        voice: payload.voice,
-        instructions: payload.instructions,
-        speed: payload.speed,
-        volume: payload.volume,
-        tone_type,
-        enable_toast,
-        toast_urgency,
+        id: uuid::Uuid::new_v4().to_string(),
+        queued_at: chrono::Utc::now(),
+        client: Some(client),
+        ack: None,
+        ..original
     };
 
-    if let Err(e) = state.tx.send(transmission) {
-        eprintln!("Failed to enqueue transmission: {}", e);
-        return "Error: Failed to queue transmission".to_string();
-    }
+    queued_response(state, transmission)
+}
 
-    "Transmission queued successfully!".to_string()
+/// POST /transmissions/{id}/replay - play a past transmission again
+async fn replay_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let original = state.store.history_transmission(Some(&id));
+    replay(&state, original, client_name(&headers, &addr))
+}
+
+/// POST /transmissions/last/replay - repeat the most recent transmission
+async fn replay_last_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let original = state.store.history_transmission(None);
+    replay(&state, original, client_name(&headers, &addr))
 }
 
 #[tokio::main]
 async fn main() {
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if cli.print_config {
+        // .env values are environment overrides too
+        load_env_file();
+        config::print(cli.config.as_deref());
+        return;
+    }
+
+    let command = match cli.command {
+        None if cli.mcp => Some(cli::Command::Mcp),
+        command => command,
+    };
+    match command {
+        None | Some(cli::Command::Serve) => serve(cli.config).await,
+        Some(command) => cli::run(command, cli.url, cli.config, cli.json).await,
+    }
+}
+
+/// Run the API server
+async fn serve(config_path: Option<std::path::PathBuf>) {
     // Load .env file - try executable directory first, then current directory
-    load_env_file();
+    let (env_path, env_warning) = load_env_file();
+
+    // Refuse to start on bad settings rather than silently falling back to defaults
+    let loaded = config::load(config_path.as_deref()).unwrap_or_else(|errors| {
+        config::report_errors(&errors);
+        std::process::exit(1);
+    });
+    let config = loaded.config.clone();
+    config::set(loaded.config);
+
+    // Set up logging - the guard keeps the log file writer flushing until shutdown
+    let _log_guard = logging::init(&config.logging);
+    if let Some(warning) = env_warning {
+        warn!("{}", warning);
+    }
+    match env_path {
+        Some(path) => info!("Loaded environment from: {}", path.display()),
+        None => info!("No .env file found"),
+    }
+    match &loaded.source {
+        Some(path) => info!("Loaded configuration file: {}", path.display()),
+        None => info!("No config file found - using defaults and environment variables"),
+    }
 
     // Create the transmission queue channel
     let (tx, rx) = mpsc::unbounded_channel::<TransmissionRequest>();
 
+    // Create app state with the sender, persistent store, acknowledgements, schedules, webhooks
+    // and lexicon
+    let store = Arc::new(store::Store::from_config(&config));
+    let state = Arc::new(AppState {
+        tx: tx.clone(),
+        schedules: Arc::new(schedule::ScheduleStore::load(
+            config.server.schedules_file.clone(),
+        )),
+        store: store.clone(),
+        acks: Arc::new(ack::AckTracker::default()),
+        queue: Arc::new(queue::QueueTracker::default()),
+        webhooks: Arc::new(webhook::WebhookStore::load(
+            config.server.webhooks_file.clone(),
+        )),
+        lexicon: Arc::new(lexicon::LexiconStore::load(
+            config.server.lexicon_file.clone(),
+        )),
+        events: Arc::new(events::EventBus::default()),
+        dnd: tokio::sync::watch::Sender::new(false),
+        config_path,
+    });
+
+    // Replay transmissions left pending or in flight by a previous run
+    let queue_ttl = config
+        .server
+        .queue_ttl_secs
+        .map(|secs| chrono::Duration::seconds(secs as i64));
+    match store.restore(queue_ttl) {
+        Ok((restored, dropped)) => {
+            if dropped > 0 {
+                info!(
+                    "Dropped {} expired transmission(s) from previous run",
+                    dropped
+                );
+            }
+            if !restored.is_empty() {
+                info!(
+                    "Restored {} pending transmission(s) from previous run",
+                    restored.len()
+                );
+            }
+            for req in restored {
+                state.acks.register(&req);
+                state.queue.push(&req);
+                let _ = tx.send(req);
+            }
+        }
+        Err(e) => error!("Failed to restore pending transmissions: {}", e),
+    }
+
     // Spawn the queue processor task
-    tokio::spawn(transmission_queue_processor(rx));
+    tokio::spawn(transmission_queue_processor(rx, state.clone()));
+
+    // Spawn the scheduler for recurring transmissions
+    tokio::spawn(schedule::scheduler_task(state.clone()));
 
-    // Create app state with the sender
-    let state = Arc::new(AppState { tx });
+    // Reload configuration on SIGHUP and, unless disabled, when its files change
+    tokio::spawn(reload::sighup_task(state.clone()));
+    if config.server.watch_config {
+        tokio::spawn(reload::watch_task(state.clone()));
+    }
 
     // Build the router with a POST endpoint and shared state
     let app = Router::new()
+        .route("/", get(dashboard::dashboard_handler))
+        .route("/dnd", get(dnd::dnd_handler).put(dnd::set_dnd_handler))
         .route("/play", post(play_tone_handler))
+        .route("/history", get(store::history_handler))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/health", get(health::health_handler))
+        .route("/ready", get(health::ready_handler))
+        .route("/admin/reload", post(reload::reload_handler))
+        .route("/events", get(events::events_handler))
+        .route("/ws", get(ws::ws_handler))
+        .route("/queue", get(queue::queue_handler))
+        .route("/queue/{id}", delete(queue::cancel_handler))
+        .route("/voices", get(voices::voices_handler))
+        .route("/profiles", get(profiles::profiles_handler))
+        .route("/templates", get(templates::templates_handler))
+        .route(
+            "/hooks/alertmanager",
+            post(alertmanager::alertmanager_handler),
+        )
+        .route("/hooks/slack", post(chat::slack_handler))
+        .route("/hooks/discord", post(chat::discord_handler))
+        .route("/hooks/{name}", post(webhook::webhook_handler))
+        .route(
+            "/webhooks",
+            get(webhook::list_webhooks_handler).post(webhook::create_webhook_handler),
+        )
+        .route("/webhooks/{name}", delete(webhook::delete_webhook_handler))
+        .route(
+            "/lexicon",
+            get(lexicon::list_lexicon_handler).post(lexicon::create_lexicon_handler),
+        )
+        .route("/lexicon/{word}", delete(lexicon::delete_lexicon_handler))
+        .route(
+            "/mcp",
+            post(mcp::http_handler).get(mcp::http_stream_handler),
+        )
+        .route("/transmissions/last/replay", post(replay_last_handler))
+        .route("/transmissions/{id}/replay", post(replay_handler))
+        .route("/ack/{id}", get(ack::ack_handler).post(ack::ack_handler))
+        .route("/acks", get(ack::list_acks_handler))
+        .route(
+            "/schedules",
+            get(schedule::list_schedules_handler).post(schedule::create_schedule_handler),
+        )
+        .route(
+            "/schedules/{name}",
+            delete(schedule::delete_schedule_handler),
+        )
         .with_state(state);
 
-    // Get bind address from environment or use default
-    let bind_address =
-        std::env::var("BIND_ADDRESS").unwrap_or_else(|_| "127.0.0.1:42069".to_string());
+    let bind_address = config.server.bind_address.clone();
 
     let listener = tokio::net::TcpListener::bind(&bind_address)
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Failed to bind to {}: {}", bind_address, e);
-            eprintln!("Make sure the address is valid and the port is not already in use.");
+            error!(
+                "Failed to bind to {}: {} - make sure the address is valid and the port is not already in use",
+                bind_address, e
+            );
             std::process::exit(1);
         });
 
     // Show TTS provider information
-    let tts_provider = TtsProvider::from_env();
+    let tts_provider = TtsProvider::configured();
     let tts_name = match tts_provider {
         TtsProvider::Edge => "Edge TTS (free)",
         TtsProvider::OpenAI => "OpenAI (premium)",
     };
 
-    println!("Quindar Tone API server running on http://{}", bind_address);
-    println!("TTS Provider: {}", tts_name);
+    info!("Quindar Tone API server running on http://{}", bind_address);
+    info!("TTS Provider: {}", tts_name);
 
     if is_headless_mode() {
-        println!("Audio Output: HEADLESS MODE (no audio playback, TTS generation only)");
-        println!("  → Perfect for WSL, headless servers, and testing environments");
+        info!("Audio Output: HEADLESS MODE (no audio playback, TTS generation only)");
     } else {
-        println!("Audio Output: ENABLED");
+        info!("Audio Output: ENABLED");
     }
 
-    println!("Transmission queue enabled - multiple requests will play sequentially");
-    println!("Send a POST request with JSON body: {{\"text\": \"your message\"}}");
+    info!("Transmission queue enabled - multiple requests will play sequentially");
+    info!("Send a POST request with JSON body: {{\"text\": \"your message\"}}");
 
     // Show example curl command with current bind address
     let example_url = if bind_address.starts_with("0.0.0.0") {
@@ -827,10 +1710,15 @@ async fn main() {
     } else {
         format!("http://{}", bind_address)
     };
-    println!(
+    info!(
         "Example: curl -X POST {}/play -H 'Content-Type: application/json' -d '{{\"text\": \"Test message\"}}'",
         example_url
     );
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,461 @@
+//! Model Context Protocol server exposing the transmission queue as agent tools.
+//!
+//! Streamable HTTP is served in-process at POST /mcp. The stdio transport (`quindar mcp`)
+//! is a bridge to a running instance's HTTP API, so agents can spawn it per session
+//! without competing for the audio device or the queue.
+
+use crate::{AppState, PlayRequest};
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The operations MCP tools map onto
+trait Backend {
+    async fn speak(&self, request: PlayRequest) -> Result<Value, String>;
+    async fn list_voices(&self, locale: Option<&str>) -> Result<Value, String>;
+    async fn queue_status(&self) -> Result<Value, String>;
+    async fn cancel(&self, id: &str) -> Result<Value, String>;
+}
+
+/// Tools backed by this process's queue
+struct LocalBackend {
+    state: Arc<AppState>,
+}
+
+impl Backend for LocalBackend {
+    async fn speak(&self, request: PlayRequest) -> Result<Value, String> {
+        let mut transmission = request.into_transmission()?;
+        transmission.client = Some("mcp".to_string());
+        let ack_url = transmission
+            .ack
+            .as_ref()
+            .map(|ack| self.state.acks.ack_url(&ack.id));
+
+        let id = self.state.enqueue(transmission)?;
+        Ok(json!({ "id": id, "ack_url": ack_url }))
+    }
+
+    async fn list_voices(&self, locale: Option<&str>) -> Result<Value, String> {
+        serde_json::to_value(crate::voices::list_voices(locale).await).map_err(|e| e.to_string())
+    }
+
+    async fn queue_status(&self) -> Result<Value, String> {
+        serde_json::to_value(self.state.queue.status()).map_err(|e| e.to_string())
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Value, String> {
+        crate::queue::cancel_transmission(&self.state, id)?;
+        Ok(json!({ "id": id, "cancelled": true }))
+    }
+}
+
+/// Tools forwarded to a running instance over HTTP
+struct RemoteBackend {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl RemoteBackend {
+    async fn get_json(&self, path: &str) -> Result<Value, String> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to reach Quindar service at {}: {}",
+                    self.base_url, e
+                )
+            })?;
+        if !response.status().is_success() {
+            return Err(response.text().await.unwrap_or_default());
+        }
+        response.json().await.map_err(|e| e.to_string())
+    }
+}
+
+impl Backend for RemoteBackend {
+    async fn speak(&self, request: PlayRequest) -> Result<Value, String> {
+        let response = self
+            .client
+            .post(format!("{}/play", self.base_url))
+            .header("X-Client-Name", "mcp")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to reach Quindar service at {}: {}",
+                    self.base_url, e
+                )
+            })?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let id = header("X-Transmission-Id");
+        let ack_url = header("X-Ack-Url");
+
+        let body = response.text().await.unwrap_or_default();
+        match id {
+            Some(id) => Ok(json!({ "id": id, "ack_url": ack_url })),
+            None => Err(body),
+        }
+    }
+
+    async fn list_voices(&self, locale: Option<&str>) -> Result<Value, String> {
+        match locale {
+            Some(locale) => {
+                let query =
+                    serde_urlencoded::to_string([("locale", locale)]).map_err(|e| e.to_string())?;
+                self.get_json(&format!("/voices?{}", query)).await
+            }
+            None => self.get_json("/voices").await,
+        }
+    }
+
+    async fn queue_status(&self) -> Result<Value, String> {
+        self.get_json("/queue").await
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Value, String> {
+        let response = self
+            .client
+            .delete(format!("{}/queue/{}", self.base_url, id))
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to reach Quindar service at {}: {}",
+                    self.base_url, e
+                )
+            })?;
+        if !response.status().is_success() {
+            return Err(response.text().await.unwrap_or_default());
+        }
+        Ok(json!({ "id": id, "cancelled": true }))
+    }
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "speak",
+            "description": "Get the user's attention by speaking a message aloud, preceded by a Quindar tone or chime. Messages are queued and played one at a time. Returns the transmission ID.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "Message to speak (omit when using a template)" },
+                    "template": { "type": "string", "description": "Named message template configured on the server, used instead of text (see GET /templates)" },
+                    "vars": { "type": "object", "description": "Values for the template's {{placeholders}}" },
+                    "ssml": { "type": "boolean", "description": "The text is SSML (break, emphasis, say-as, prosody, sub, phoneme, p, s)" },
+                    "profile": { "type": "string", "description": "Named notification profile configured on the server (e.g. ci-failure) - sets voice, tone, speed, volume and urgency; fields given here override it" },
+                    "voice": { "type": "string", "description": "Voice name (see list_voices)" },
+                    "tone": { "type": "string", "enum": ["QUINDAR", "THREE-NOTE-CHIME", "NO-TONE"] },
+                    "speed": { "type": "number", "minimum": 0.25, "maximum": 4.0 },
+                    "volume": { "type": "number", "minimum": 0.1, "maximum": 5.0 },
+                    "pitch": { "type": "integer", "minimum": -100, "maximum": 100, "description": "Voice pitch shift in Hz - raise it to make urgent messages stand out" },
+                    "voice_volume": { "type": "integer", "minimum": -100, "maximum": 100, "description": "Voice loudness change in percent, applied during synthesis" },
+                    "instructions": { "type": "string", "description": "Delivery instructions (OpenAI voices only)" },
+                    "enable_toast": { "type": "boolean", "description": "Also show a desktop notification" },
+                    "toast_urgency": { "type": "string", "enum": ["info", "warning", "critical"] },
+                    "require_ack": { "type": "boolean", "description": "Repeat with escalation until a human acknowledges" }
+                }
+            }
+        },
+        {
+            "name": "list_voices",
+            "description": "List voices available from the configured text-to-speech provider.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "locale": { "type": "string", "description": "Locale prefix filter, e.g. en or en-GB" }
+                }
+            }
+        },
+        {
+            "name": "queue_status",
+            "description": "Show the transmission currently playing and those waiting in the queue.",
+            "inputSchema": { "type": "object", "properties": {} }
+        },
+        {
+            "name": "cancel",
+            "description": "Cancel a queued transmission, or stop it if it is currently playing.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "description": "Transmission ID returned by speak" }
+                },
+                "required": ["id"]
+            }
+        }
+    ])
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+async fn call_tool<B: Backend>(backend: &B, name: &str, arguments: Value) -> Result<Value, String> {
+    match name {
+        "speak" => {
+            let request: PlayRequest = serde_json::from_value(arguments)
+                .map_err(|e| format!("Invalid speak arguments: {}", e))?;
+            backend.speak(request).await
+        }
+        "list_voices" => {
+            backend
+                .list_voices(arguments.get("locale").and_then(Value::as_str))
+                .await
+        }
+        "queue_status" => backend.queue_status().await,
+        "cancel" => {
+            let id = arguments
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or("Missing required argument: id")?;
+            backend.cancel(id).await
+        }
+        _ => Err(format!("Unknown tool: {}", name)),
+    }
+}
+
+/// Handle one JSON-RPC message, returning the response (None for notifications)
+async fn handle_message<B: Backend>(backend: &B, message: Value) -> Option<Value> {
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            message.get("id").cloned().unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "Invalid request",
+        ));
+    };
+
+    // Notifications (no id) never get a response
+    let id = message.get("id").cloned()?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => {
+            let requested = params
+                .get("protocolVersion")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let version = SUPPORTED_PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| **v == requested)
+                .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "quindar", "version": env!("CARGO_PKG_VERSION") },
+                "instructions": "Use speak to get the user's attention with a spoken message when you need approval, have finished a long task, or something urgent happened."
+            })
+        }
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tool_definitions() }),
+        "tools/call" => {
+            let Some(name) = params.get("name").and_then(Value::as_str) else {
+                return Some(error_response(id, INVALID_PARAMS, "Missing tool name"));
+            };
+            let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+            // Tool failures are reported in the result so the agent can see them
+            match call_tool(backend, name, arguments).await {
+                Ok(value) => json!({
+                    "content": [{ "type": "text", "text": value.to_string() }],
+                    "structuredContent": value,
+                    "isError": false
+                }),
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": e }],
+                    "isError": true
+                }),
+            }
+        }
+        _ => {
+            return Some(error_response(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("Method not found: {}", method),
+            ));
+        }
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Only accept browser requests from local pages (guards against DNS rebinding).
+/// Same-origin pages are also allowed when reached by IP address, e.g. over the LAN.
+pub fn origin_allowed(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get("origin").and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let authority = origin.split("://").nth(1).unwrap_or(origin);
+    // IPv6 hosts are bracketed and contain colons themselves: http://[::1]:42069
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((host, port)) if port.is_empty() || port.starts_with(':') => host,
+            _ => return false,
+        },
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    if matches!(host, "localhost" | "127.0.0.1" | "::1") {
+        return true;
+    }
+
+    // A rebinding attack needs a DNS name, so an IP literal matching Host is safe
+    host.parse::<std::net::IpAddr>().is_ok()
+        && headers
+            .get("host")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|h| h == authority)
+}
+
+/// POST /mcp - streamable HTTP transport (JSON responses, no server-initiated stream)
+pub async fn http_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(message): Json<Value>,
+) -> impl IntoResponse {
+    if !origin_allowed(&headers) {
+        return (StatusCode::FORBIDDEN, "Error: Origin not allowed").into_response();
+    }
+
+    let backend = LocalBackend { state };
+    match handle_message(&backend, message).await {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// GET /mcp - this server doesn't open server-initiated SSE streams
+pub async fn http_stream_handler() -> impl IntoResponse {
+    StatusCode::METHOD_NOT_ALLOWED
+}
+
+/// Run the stdio transport, forwarding tool calls to the instance at `base_url`.
+/// stdout carries protocol messages only - diagnostics go to stderr.
+pub async fn run_stdio(base_url: String) {
+    let backend = RemoteBackend {
+        base_url: base_url.trim_end_matches('/').to_string(),
+        client: reqwest::Client::new(),
+    };
+    eprintln!(
+        "Quindar MCP server (stdio) forwarding to {}",
+        backend.base_url
+    );
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&backend, message).await,
+            Err(e) => Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                &format!("Parse error: {}", e),
+            )),
+        };
+
+        if let Some(response) = response {
+            let mut out = response.to_string();
+            out.push('\n');
+            if stdout.write_all(out.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(origin: Option<&str>, host: &str) -> bool {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", host.parse().unwrap());
+        if let Some(origin) = origin {
+            headers.insert("origin", origin.parse().unwrap());
+        }
+        origin_allowed(&headers)
+    }
+
+    #[test]
+    fn local_origins_are_allowed() {
+        for origin in [
+            "http://localhost",
+            "http://localhost:3000",
+            "http://127.0.0.1:42069",
+            "http://[::1]:42069",
+            "http://[::1]",
+        ] {
+            assert!(allowed(Some(origin), "127.0.0.1:42069"), "{}", origin);
+        }
+        // Non-browser clients send no Origin
+        assert!(allowed(None, "127.0.0.1:42069"));
+    }
+
+    #[test]
+    fn same_origin_ip_addresses_are_allowed() {
+        assert!(allowed(
+            Some("http://192.168.1.5:42069"),
+            "192.168.1.5:42069"
+        ));
+        assert!(allowed(Some("http://[fe80::1]:42069"), "[fe80::1]:42069"));
+        assert!(!allowed(
+            Some("http://192.168.1.5:42069"),
+            "192.168.1.6:42069"
+        ));
+    }
+
+    #[test]
+    fn foreign_origins_are_rejected() {
+        for (origin, host) in [
+            ("https://evil.example", "127.0.0.1:42069"),
+            ("http://localhost.evil.example", "127.0.0.1:42069"),
+            // A rebound DNS name matches Host, but isn't an IP literal
+            ("http://rebind.example:42069", "rebind.example:42069"),
+        ] {
+            assert!(!allowed(Some(origin), host), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn malformed_origins_are_rejected() {
+        for origin in [
+            "null",
+            "",
+            "http://[::1",
+            "http://[::1]evil.example",
+            "http://",
+        ] {
+            assert!(!allowed(Some(origin), "127.0.0.1:42069"), "{:?}", origin);
+        }
+    }
+}
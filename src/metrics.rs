@@ -0,0 +1,358 @@
+//! Prometheus metrics, exposed in the text exposition format at GET /metrics
+
+use crate::AppState;
+use axum::{extract::State, http::header, response::IntoResponse};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+/// Process-wide metrics. A global because TTS and playback code runs without app state.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Upper bounds (seconds) of the TTS latency histogram buckets
+const TTS_BUCKETS: &[f64] = &[0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 30.0];
+/// Upper bounds (seconds) of the playback duration histogram buckets
+const PLAYBACK_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Non-cumulative count per bucket; the extra last slot is +Inf
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            let le = self
+                .bounds
+                .get(i)
+                .map(|bound| bound.to_string())
+                .unwrap_or_else(|| "+Inf".to_string());
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, le, cumulative
+            );
+        }
+        let braces = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(out, "{}_sum{} {}", name, braces, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces, self.count);
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    /// (provider, tone) -> count
+    queued: BTreeMap<(String, String), u64>,
+    /// (provider, tone, status) -> count
+    finished: BTreeMap<(String, String, String), u64>,
+    tts_latency: BTreeMap<String, Histogram>,
+    tts_retries: BTreeMap<String, u64>,
+    tts_failures: BTreeMap<String, u64>,
+    playback: Option<Histogram>,
+    cache_hits: u64,
+    cache_misses: u64,
+    audio_device_errors: u64,
+    last_completed: Option<f64>,
+    config_reloads: u64,
+    config_reload_failures: u64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+impl Metrics {
+    pub fn transmission_queued(&self, provider: &str, tone: &str) {
+        let key = (provider.to_lowercase(), tone.to_string());
+        *self.inner.lock().unwrap().queued.entry(key).or_default() += 1;
+    }
+
+    /// Count a transmission leaving the queue as completed, failed or cancelled
+    pub fn transmission_finished(&self, provider: &str, tone: &str, status: &str) {
+        let key = (
+            provider.to_lowercase(),
+            tone.to_string(),
+            status.to_string(),
+        );
+        let mut inner = self.inner.lock().unwrap();
+        *inner.finished.entry(key).or_default() += 1;
+        if status == "completed" {
+            inner.last_completed = Some(chrono::Utc::now().timestamp_millis() as f64 / 1000.0);
+        }
+    }
+
+    pub fn tts_succeeded(&self, provider: &str, latency: Duration) {
+        self.inner
+            .lock()
+            .unwrap()
+            .tts_latency
+            .entry(provider.to_lowercase())
+            .or_insert_with(|| Histogram::new(TTS_BUCKETS))
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn tts_failed(&self, provider: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .tts_failures
+            .entry(provider.to_lowercase())
+            .or_default() += 1;
+    }
+
+    pub fn tts_retried(&self, provider: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .tts_retries
+            .entry(provider.to_lowercase())
+            .or_default() += 1;
+    }
+
+    pub fn playback_finished(&self, duration: Duration) {
+        self.inner
+            .lock()
+            .unwrap()
+            .playback
+            .get_or_insert_with(|| Histogram::new(PLAYBACK_BUCKETS))
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn audio_cache(&self, hit: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if hit {
+            inner.cache_hits += 1;
+        } else {
+            inner.cache_misses += 1;
+        }
+    }
+
+    pub fn audio_device_error(&self) {
+        self.inner.lock().unwrap().audio_device_errors += 1;
+    }
+
+    pub fn config_reloaded(&self, success: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if success {
+            inner.config_reloads += 1;
+        } else {
+            inner.config_reload_failures += 1;
+        }
+    }
+
+    /// Render every metric, plus gauges read from the live app state
+    fn render(&self, state: &AppState) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        describe(
+            &mut out,
+            "quindar_transmissions_queued_total",
+            "counter",
+            "Transmissions added to the queue",
+        );
+        for ((provider, tone), count) in &inner.queued {
+            let _ = writeln!(
+                out,
+                "quindar_transmissions_queued_total{{provider=\"{}\",tone=\"{}\"}} {}",
+                provider, tone, count
+            );
+        }
+
+        describe(
+            &mut out,
+            "quindar_transmissions_total",
+            "counter",
+            "Transmissions that left the queue, by outcome",
+        );
+        for ((provider, tone, status), count) in &inner.finished {
+            let _ = writeln!(
+                out,
+                "quindar_transmissions_total{{provider=\"{}\",tone=\"{}\",status=\"{}\"}} {}",
+                provider, tone, status, count
+            );
+        }
+
+        let status = state.queue.status();
+        gauge(
+            &mut out,
+            "quindar_queue_depth",
+            "Transmissions waiting to play",
+            status.pending.len(),
+        );
+        gauge(
+            &mut out,
+            "quindar_transmission_in_progress",
+            "Whether a transmission is being synthesized or played",
+            status.current.is_some() as usize,
+        );
+        gauge(
+            &mut out,
+            "quindar_acks_pending",
+            "Transmissions waiting for acknowledgement",
+            state.acks.pending_count(),
+        );
+        gauge(
+            &mut out,
+            "quindar_dnd_enabled",
+            "Whether Do Not Disturb is holding non-critical transmissions",
+            *state.dnd.borrow() as usize,
+        );
+        if let Some(timestamp) = inner.last_completed {
+            gauge(
+                &mut out,
+                "quindar_last_completed_timestamp_seconds",
+                "Unix time of the last completed transmission",
+                timestamp,
+            );
+        }
+
+        describe(
+            &mut out,
+            "quindar_tts_duration_seconds",
+            "histogram",
+            "Time to synthesize speech, including retries",
+        );
+        for (provider, histogram) in &inner.tts_latency {
+            histogram.render(
+                &mut out,
+                "quindar_tts_duration_seconds",
+                &format!("provider=\"{}\"", provider),
+            );
+        }
+
+        describe(
+            &mut out,
+            "quindar_tts_retries_total",
+            "counter",
+            "TTS requests retried after a failed attempt",
+        );
+        for (provider, count) in &inner.tts_retries {
+            let _ = writeln!(
+                out,
+                "quindar_tts_retries_total{{provider=\"{}\"}} {}",
+                provider, count
+            );
+        }
+
+        describe(
+            &mut out,
+            "quindar_tts_failures_total",
+            "counter",
+            "TTS requests that failed after all retries",
+        );
+        for (provider, count) in &inner.tts_failures {
+            let _ = writeln!(
+                out,
+                "quindar_tts_failures_total{{provider=\"{}\"}} {}",
+                provider, count
+            );
+        }
+
+        describe(
+            &mut out,
+            "quindar_playback_duration_seconds",
+            "histogram",
+            "Time spent playing tones and voice",
+        );
+        inner
+            .playback
+            .as_ref()
+            .unwrap_or(&Histogram::new(PLAYBACK_BUCKETS))
+            .render(&mut out, "quindar_playback_duration_seconds", "");
+
+        counter(
+            &mut out,
+            "quindar_audio_cache_hits_total",
+            "Speech chunks played from cached audio",
+            inner.cache_hits,
+        );
+        counter(
+            &mut out,
+            "quindar_audio_cache_misses_total",
+            "Speech chunks that needed synthesis",
+            inner.cache_misses,
+        );
+        counter(
+            &mut out,
+            "quindar_audio_device_errors_total",
+            "Failures opening the audio output device",
+            inner.audio_device_errors,
+        );
+
+        describe(
+            &mut out,
+            "quindar_config_reloads_total",
+            "counter",
+            "Configuration reloads, by result",
+        );
+        let _ = writeln!(
+            out,
+            "quindar_config_reloads_total{{result=\"success\"}} {}",
+            inner.config_reloads
+        );
+        let _ = writeln!(
+            out,
+            "quindar_config_reloads_total{{result=\"failure\"}} {}",
+            inner.config_reload_failures
+        );
+
+        out
+    }
+}
+
+fn describe(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    describe(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    describe(out, name, "counter", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// GET /metrics - Prometheus text exposition format
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        METRICS.render(&state),
+    )
+}
@@ -0,0 +1,178 @@
+//! Named items kept in memory and, when a file is configured, in a JSON array on disk -
+//! the storage behind schedules, webhook routes and the pronunciation lexicon
+
+use axum::http::StatusCode;
+use serde::{Serialize, de::DeserializeOwned};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{error, info, warn};
+
+/// Something a [`NamedStore`] holds: built from a spec that is what the API accepts and
+/// the file contains
+pub trait Named: Sized {
+    type Spec: Clone + Serialize + DeserializeOwned;
+    /// What one item is called in messages, e.g. "Schedule"
+    const KIND: &'static str;
+    /// The file's description in messages, e.g. "schedules file"
+    const FILE: &'static str;
+
+    /// Check a spec and build the stored item from it
+    fn compile(spec: Self::Spec) -> Result<Self, String>;
+    fn spec(&self) -> &Self::Spec;
+    fn name(spec: &Self::Spec) -> &str;
+}
+
+/// Items shared between a management API and the code that uses them, unique by name
+pub struct NamedStore<T: Named> {
+    items: Mutex<Vec<T>>,
+    path: Mutex<Option<PathBuf>>,
+    /// Bumped on every change, so callers can cache what they derive from the items
+    generation: AtomicU64,
+}
+
+/// Read specs from a JSON array file. A missing file holds none.
+pub fn read_file<T: Named>(path: &Path) -> Result<Vec<T::Spec>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {} {:?}: {}", T::FILE, path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {} {:?}: {}", T::FILE, path, e)),
+    }
+}
+
+impl<T: Named> NamedStore<T> {
+    /// Load items from `path` (JSON array), if one is configured
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let store = NamedStore {
+            items: Mutex::new(Vec::new()),
+            path: Mutex::new(path.clone()),
+            generation: AtomicU64::new(0),
+        };
+
+        let Some(path) = path else {
+            return store;
+        };
+
+        match read_file::<T>(&path) {
+            Ok(specs) => store.replace(Some(path), Some(specs)),
+            Err(e) => warn!("{}", e),
+        }
+
+        store
+    }
+
+    /// Switch to the file at `path`, replacing every item with `specs` - or keeping the
+    /// current ones when there are none to load (no file configured)
+    pub fn replace(&self, path: Option<PathBuf>, specs: Option<Vec<T::Spec>>) {
+        if let Some(specs) = specs {
+            let mut items: Vec<T> = Vec::new();
+            for spec in specs {
+                let name = T::name(&spec).to_string();
+                match T::compile(spec) {
+                    Ok(item) => {
+                        items.retain(|i| T::name(i.spec()) != name);
+                        items.push(item);
+                    }
+                    Err(e) => warn!("Skipping {} '{}': {}", T::KIND.to_lowercase(), name, e),
+                }
+            }
+            *self.items.lock().unwrap() = items;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+        *self.path.lock().unwrap() = path;
+    }
+
+    /// Add or replace the item with the spec's name
+    pub fn insert(&self, spec: T::Spec) -> Result<(), String> {
+        let item = T::compile(spec)?;
+
+        let mut items = self.items.lock().unwrap();
+        items.retain(|i| T::name(i.spec()) != T::name(item.spec()));
+        items.push(item);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Remove an item by name, returning whether it existed
+    pub fn remove(&self, name: &str) -> bool {
+        let mut items = self.items.lock().unwrap();
+        let before = items.len();
+        items.retain(|i| T::name(i.spec()) != name);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        items.len() != before
+    }
+
+    pub fn get(&self, name: &str) -> Option<T::Spec> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .map(T::spec)
+            .find(|spec| T::name(spec) == name)
+            .cloned()
+    }
+
+    /// Changes with every replace, insert and remove
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Run `f` over the current items
+    pub fn with_items<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
+        f(&self.items.lock().unwrap())
+    }
+
+    /// Write the current items back to the file so API changes survive restarts
+    pub fn save(&self) {
+        let Some(path) = self.path.lock().unwrap().clone() else {
+            return;
+        };
+
+        let specs: Vec<T::Spec> =
+            self.with_items(|items| items.iter().map(|i| i.spec().clone()).collect());
+        match serde_json::to_string_pretty(&specs) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&path, json) {
+                    error!("Failed to write {} {:?}: {}", T::FILE, path, e);
+                }
+            }
+            Err(e) => error!("Failed to serialize {}: {}", T::FILE, e),
+        }
+    }
+
+    /// Create or replace an item for a management API request, saving the file
+    pub fn create_response(&self, spec: T::Spec) -> (StatusCode, String) {
+        let name = T::name(&spec).to_string();
+
+        if let Err(e) = self.insert(spec) {
+            return (StatusCode::BAD_REQUEST, format!("Error: {}", e));
+        }
+        self.save();
+
+        info!("{} '{}' saved", T::KIND, name);
+        (StatusCode::OK, format!("{} '{}' saved", T::KIND, name))
+    }
+
+    /// Remove an item for a management API request, saving the file
+    pub fn delete_response(&self, name: &str) -> (StatusCode, String) {
+        if !self.remove(name) {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("Error: {} '{}' not found", T::KIND, name),
+            );
+        }
+        self.save();
+
+        info!("{} '{}' removed", T::KIND, name);
+        (StatusCode::OK, format!("{} '{}' removed", T::KIND, name))
+    }
+}
@@ -0,0 +1,9 @@
+//! Named notification profiles: bundles of voice, tone, speed, volume and urgency settings
+//! defined in the config and referenced by a request's `profile` field
+
+use axum::{extract::Json, response::IntoResponse};
+
+/// GET /profiles - configured profiles by name
+pub async fn profiles_handler() -> impl IntoResponse {
+    Json(crate::config::get().profiles.clone())
+}
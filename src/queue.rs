@@ -0,0 +1,142 @@
+//! Inspectable view of the transmission queue, with cancellation
+
+use crate::events::EventKind;
+use crate::{AppState, TransmissionRequest};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Summary of a queued or playing transmission
+#[derive(Clone, Serialize)]
+pub struct QueueEntry {
+    pub id: String,
+    pub text: String,
+    pub voice: String,
+    pub tone: String,
+    pub toast_urgency: String,
+    pub client: Option<String>,
+    pub queued_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip)]
+    ack_id: Option<String>,
+}
+
+impl From<&TransmissionRequest> for QueueEntry {
+    fn from(req: &TransmissionRequest) -> Self {
+        QueueEntry {
+            id: req.id.clone(),
+            text: req.text.clone(),
+            voice: req.voice.clone(),
+            tone: req.tone_type.as_str().to_string(),
+            toast_urgency: req.toast_urgency.as_str().to_string(),
+            client: req.client.clone(),
+            queued_at: req.queued_at,
+            ack_id: req.ack.as_ref().map(|ack| ack.id.clone()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct QueueStatus {
+    pub current: Option<QueueEntry>,
+    pub pending: Vec<QueueEntry>,
+}
+
+/// Mirrors what has been sent to the queue processor so it can be listed and cancelled
+#[derive(Default)]
+pub struct QueueTracker {
+    pending: Mutex<Vec<QueueEntry>>,
+    current: Mutex<Option<(QueueEntry, Arc<AtomicBool>)>>,
+}
+
+impl QueueTracker {
+    /// Record a transmission that is about to be sent to the queue processor
+    pub fn push(&self, req: &TransmissionRequest) {
+        self.pending.lock().unwrap().push(QueueEntry::from(req));
+    }
+
+    /// Move a transmission from pending to current, returning its cancel flag.
+    /// Returns None if it was cancelled while waiting.
+    pub fn start(&self, req: &TransmissionRequest) -> Option<Arc<AtomicBool>> {
+        let mut pending = self.pending.lock().unwrap();
+        let position = pending.iter().position(|entry| entry.id == req.id)?;
+        let entry = pending.remove(position);
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        *self.current.lock().unwrap() = Some((entry, cancel.clone()));
+        Some(cancel)
+    }
+
+    /// Clear the current transmission once it has finished
+    pub fn finish(&self) {
+        *self.current.lock().unwrap() = None;
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        QueueStatus {
+            current: self
+                .current
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|(entry, _)| entry.clone()),
+            pending: self.pending.lock().unwrap().clone(),
+        }
+    }
+
+    /// Cancel a pending transmission, or stop the one currently playing.
+    /// Returns the cancelled entry, or None if the ID isn't queued.
+    fn cancel(&self, id: &str) -> Option<QueueEntry> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(position) = pending.iter().position(|entry| entry.id == id) {
+            return Some(pending.remove(position));
+        }
+        drop(pending);
+
+        match self.current.lock().unwrap().as_ref() {
+            Some((entry, cancel)) if entry.id == id => {
+                cancel.store(true, Ordering::Relaxed);
+                Some(entry.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Cancel a transmission and stop any escalation waiting on it
+pub fn cancel_transmission(state: &AppState, id: &str) -> Result<(), String> {
+    let entry = state
+        .queue
+        .cancel(id)
+        .ok_or_else(|| format!("Transmission {} is not queued or playing", id))?;
+
+    state.store.remove(&entry.id);
+    state.events.emit_entry(EventKind::Cancelled, &entry);
+    if let Some(ack_id) = &entry.ack_id {
+        state.acks.acknowledge(ack_id);
+    }
+
+    info!(id = %id, "Transmission cancelled");
+    Ok(())
+}
+
+/// GET /queue - the transmission currently playing and those waiting
+pub async fn queue_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.queue.status())
+}
+
+/// DELETE /queue/{id} - cancel a pending or playing transmission
+pub async fn cancel_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match cancel_transmission(&state, &id) {
+        Ok(()) => (StatusCode::OK, "Transmission cancelled".to_string()),
+        Err(e) => (StatusCode::NOT_FOUND, format!("Error: {}", e)),
+    }
+}
@@ -0,0 +1,286 @@
+//! Hot reload: re-read .env, the config file, schedules, webhooks and the lexicon on file change,
+//! SIGHUP or POST /admin/reload, and swap them in without touching the queue
+
+use crate::AppState;
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Editors often write a file in several steps - wait for them to finish
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// One reload at a time, so two triggers can't interleave their swaps
+static RELOADING: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize)]
+pub struct ReloadReport {
+    pub reloaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<PathBuf>,
+    /// Settings that now have new values
+    pub changed: Vec<String>,
+    /// Settings that changed but are only read at startup - the running values are kept
+    pub restart_required: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl ReloadReport {
+    fn failed(errors: Vec<String>) -> Self {
+        ReloadReport {
+            reloaded: false,
+            config_file: None,
+            env_file: None,
+            changed: Vec::new(),
+            restart_required: Vec::new(),
+            errors,
+        }
+    }
+}
+
+/// Re-read every configuration source and apply the result. Nothing changes unless all
+/// of it is valid.
+pub fn reload(state: &AppState, trigger: &str) -> ReloadReport {
+    let _guard = RELOADING.lock().unwrap();
+
+    let report = match prepare(state) {
+        Ok(report) => report,
+        Err(errors) => {
+            error!(
+                trigger,
+                "Configuration reload failed - keeping the current settings"
+            );
+            for e in &errors {
+                error!(trigger, "  {}", e);
+            }
+            crate::metrics::METRICS.config_reloaded(false);
+            return ReloadReport::failed(errors);
+        }
+    };
+
+    if report.changed.is_empty() {
+        info!(trigger, "Configuration reloaded - no changes");
+    } else {
+        info!(
+            trigger,
+            "Configuration reloaded: {}",
+            report.changed.join(", ")
+        );
+    }
+    if !report.restart_required.is_empty() {
+        warn!(
+            trigger,
+            "Restart required to apply: {}",
+            report.restart_required.join(", ")
+        );
+    }
+    crate::metrics::METRICS.config_reloaded(true);
+    report
+}
+
+/// Load and validate everything, then swap it in
+fn prepare(state: &AppState) -> Result<ReloadReport, Vec<String>> {
+    let (env_file, env_vars) = crate::config::read_env_file().map_err(|e| vec![e])?;
+    let loaded = crate::config::load_with_env_file(state.config_path.as_deref(), &env_vars)?;
+    let mut config = loaded.config;
+
+    // Read the schedule, webhook and lexicon files up front so a broken one fails the whole reload
+    let schedules = config
+        .server
+        .schedules_file
+        .as_deref()
+        .map(crate::persisted::read_file::<crate::schedule::Schedule>)
+        .transpose()
+        .map_err(|e| vec![e])?;
+    let webhooks = config
+        .server
+        .webhooks_file
+        .as_deref()
+        .map(crate::persisted::read_file::<crate::webhook::WebhookSpec>)
+        .transpose()
+        .map_err(|e| vec![e])?;
+    let lexicon = config
+        .server
+        .lexicon_file
+        .as_deref()
+        .map(crate::persisted::read_file::<crate::lexicon::LexiconEntry>)
+        .transpose()
+        .map_err(|e| vec![e])?;
+
+    let running = crate::config::get();
+    let restart_required = config.keep_startup_settings(&running);
+    let changed = config.changed_keys(&running);
+
+    if config.logging.level != running.logging.level {
+        crate::logging::set_level(&config.logging.level).map_err(|e| vec![e])?;
+    }
+    state
+        .schedules
+        .replace(config.server.schedules_file.clone(), schedules);
+    state
+        .webhooks
+        .replace(config.server.webhooks_file.clone(), webhooks);
+    state
+        .lexicon
+        .entries
+        .replace(config.server.lexicon_file.clone(), lexicon);
+    crate::config::set_env_file(env_vars);
+    crate::config::set(config);
+
+    Ok(ReloadReport {
+        reloaded: true,
+        config_file: loaded.source,
+        env_file,
+        changed,
+        restart_required,
+        errors: Vec::new(),
+    })
+}
+
+/// POST /admin/reload - 200 with what changed, or 422 with every problem found
+pub async fn reload_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection.into_response();
+    }
+
+    let report = tokio::task::spawn_blocking(move || reload(&state, "api"))
+        .await
+        .unwrap_or_else(|e| ReloadReport::failed(vec![format!("Reload failed: {}", e)]));
+    let status = if report.reloaded {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    (status, Json(report)).into_response()
+}
+
+/// Reload on SIGHUP, the usual way to ask a daemon to re-read its configuration
+#[cfg(unix)]
+pub async fn sighup_task(state: Arc<AppState>) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!(
+                "Failed to listen for SIGHUP - reload with POST /admin/reload: {}",
+                e
+            );
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        let state = state.clone();
+        let _ = tokio::task::spawn_blocking(move || reload(&state, "sighup")).await;
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn sighup_task(_state: Arc<AppState>) {}
+
+/// Files whose changes trigger a reload: the config file (or where one would be picked
+/// up) and the .env file (or where one would be picked up)
+fn watched_files(state: &AppState) -> HashSet<PathBuf> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from));
+    let cwd = std::env::current_dir().ok();
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    match state
+        .config_path
+        .clone()
+        .or_else(|| crate::config::env_var("QUINDAR_CONFIG").map(PathBuf::from))
+    {
+        Some(path) => files.push(path),
+        None => files.extend(
+            [&exe_dir, &cwd]
+                .into_iter()
+                .flatten()
+                .map(|dir| dir.join(crate::config::DEFAULT_CONFIG_FILE)),
+        ),
+    }
+    files.extend(crate::config::find_env_file());
+    files.extend(
+        [&exe_dir, &cwd]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(".env")),
+    );
+
+    files
+        .into_iter()
+        .filter_map(|path| std::path::absolute(path).ok())
+        .collect()
+}
+
+/// Reload when the config file or .env changes on disk
+pub async fn watch_task(state: Arc<AppState>) {
+    let files = watched_files(&state);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    // Watch the directories rather than the files, so editors that save by replacing
+    // the file (and files created after startup) are still seen
+    let watched = files.clone();
+    let mut watcher = match notify::recommended_watcher(
+        move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if !event.kind.is_access() && event.paths.iter().any(|path| watched.contains(path)) {
+                let _ = tx.send(());
+            }
+        },
+    ) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!(
+                "Failed to watch configuration files - reload with SIGHUP or POST /admin/reload: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    let directories: HashSet<PathBuf> = files
+        .iter()
+        .filter_map(|path| path.parent().map(PathBuf::from))
+        .collect();
+    for directory in &directories {
+        if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+            warn!("Failed to watch {}: {}", directory.display(), e);
+        }
+    }
+    info!(
+        "Watching for configuration changes: {}",
+        files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    while rx.recv().await.is_some() {
+        // Collapse the burst of events from a single save into one reload
+        tokio::time::sleep(DEBOUNCE).await;
+        while rx.try_recv().is_ok() {}
+
+        let state = state.clone();
+        let _ = tokio::task::spawn_blocking(move || reload(&state, "file change")).await;
+    }
+}
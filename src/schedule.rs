@@ -0,0 +1,273 @@
+//! Recurring transmissions driven by cron expressions
+
+use crate::persisted::{Named, NamedStore};
+use crate::{AppState, PlayRequest};
+use axum::{
+    extract::{Json, Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// A named recurring announcement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleSpec {
+    pub name: String,
+    /// Cron expression: 5 fields (min hour dom mon dow) or 6-7 fields with seconds/year
+    pub cron: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Transmission fields, same shape as a POST /play body. `text` may contain
+    /// {{time}}, {{date}}, {{weekday}}, {{datetime}} and {{name}} placeholders.
+    #[serde(flatten)]
+    pub request: PlayRequest,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Placeholders schedule text may use
+const PLACEHOLDERS: &[&str] = &["time", "date", "weekday", "datetime", "name"];
+
+/// A schedule spec with its parsed cron expression
+pub struct Schedule {
+    spec: ScheduleSpec,
+    cron: cron::Schedule,
+}
+
+impl Named for Schedule {
+    type Spec = ScheduleSpec;
+    const KIND: &'static str = "Schedule";
+    const FILE: &'static str = "schedules file";
+
+    /// Check a spec's name and text, and parse its cron expression
+    fn compile(spec: ScheduleSpec) -> Result<Self, String> {
+        if spec.name.trim().is_empty() {
+            return Err("Schedule name must not be empty".to_string());
+        }
+        crate::templates::validate(&spec.request.text)?;
+        let variables = crate::templates::variables(&spec.request.text);
+        if let Some(unknown) = variables
+            .iter()
+            .find(|name| !PLACEHOLDERS.contains(&name.as_str()))
+        {
+            return Err(format!(
+                "Unknown placeholder '{}' in schedule text (expected {})",
+                unknown,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        let cron = parse_cron(&spec.cron)?;
+        Ok(Schedule { spec, cron })
+    }
+
+    fn spec(&self) -> &ScheduleSpec {
+        &self.spec
+    }
+
+    fn name(spec: &ScheduleSpec) -> &str {
+        &spec.name
+    }
+}
+
+/// Schedule list shared between the API handlers and the scheduler task
+pub type ScheduleStore = NamedStore<Schedule>;
+
+#[derive(Serialize)]
+struct ScheduleInfo {
+    #[serde(flatten)]
+    spec: ScheduleSpec,
+    next_run: Option<String>,
+}
+
+/// Parse a cron expression, accepting the common 5-field form by assuming second 0
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule, String> {
+    let normalized = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+
+    cron::Schedule::from_str(&normalized)
+        .map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))
+}
+
+/// Fill the date/time and name placeholders in schedule text
+pub fn render_text(template: &str, name: &str, now: &DateTime<Local>) -> String {
+    let lookup = |placeholder: &str| {
+        let value = match placeholder {
+            "time" => now.format("%-I:%M %p").to_string(),
+            "date" => now.format("%B %-d, %Y").to_string(),
+            "weekday" => now.format("%A").to_string(),
+            "datetime" => now.format("%A, %B %-d at %-I:%M %p").to_string(),
+            "name" => name.to_string(),
+            _ => return Ok(None),
+        };
+        Ok(Some(Value::String(value)))
+    };
+    match crate::templates::fill(template, lookup) {
+        Ok((text, _)) => text,
+        Err(e) => {
+            error!("Failed to render text for schedule '{}': {}", name, e);
+            template.to_string()
+        }
+    }
+}
+
+impl NamedStore<Schedule> {
+    fn list(&self) -> Vec<ScheduleInfo> {
+        let now = Local::now();
+        self.with_items(|schedules| {
+            schedules
+                .iter()
+                .map(|s| ScheduleInfo {
+                    spec: s.spec.clone(),
+                    next_run: s
+                        .spec
+                        .enabled
+                        .then(|| s.cron.after(&now).next())
+                        .flatten()
+                        .map(|t| t.to_rfc3339()),
+                })
+                .collect()
+        })
+    }
+
+    /// Collect (name, transmission) for every schedule that fired in (since, now]
+    fn due(&self, since: &DateTime<Local>, now: &DateTime<Local>) -> Vec<(String, PlayRequest)> {
+        self.with_items(|schedules| {
+            schedules
+                .iter()
+                .filter(|s| s.spec.enabled)
+                .filter(|s| s.cron.after(since).next().is_some_and(|t| t <= *now))
+                .map(|s| {
+                    let mut request = s.spec.request.clone();
+                    request.text = render_text(&request.text, &s.spec.name, now);
+                    (s.spec.name.clone(), request)
+                })
+                .collect()
+        })
+    }
+}
+
+/// Background task that enqueues transmissions when their schedules fire
+pub async fn scheduler_task(state: Arc<AppState>) {
+    info!("Scheduler started ({} schedule(s))", state.schedules.len());
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut last_tick = Local::now();
+
+    loop {
+        interval.tick().await;
+        let now = Local::now();
+
+        for (name, request) in state.schedules.due(&last_tick, &now) {
+            info!("Schedule fired, adding to queue: {}", request.text);
+            let mut transmission = match request.into_transmission() {
+                Ok(transmission) => transmission,
+                Err(e) => {
+                    error!("Schedule '{}' failed: {}", name, e);
+                    continue;
+                }
+            };
+            transmission.client = Some(format!("schedule:{}", name));
+            if let Err(e) = state.enqueue(transmission) {
+                error!("Failed to enqueue scheduled transmission: {}", e);
+            }
+        }
+
+        last_tick = now;
+    }
+}
+
+/// GET /schedules - list schedules with their next run time
+pub async fn list_schedules_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.schedules.list())
+}
+
+/// POST /schedules - create or replace a schedule
+pub async fn create_schedule_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(spec): Json<ScheduleSpec>,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection;
+    }
+    state.schedules.create_response(spec)
+}
+
+/// DELETE /schedules/{name} - remove a schedule
+pub async fn delete_schedule_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(rejection) = crate::authorize_admin(&headers) {
+        return rejection;
+    }
+    state.schedules.delete_response(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parse_cron_accepts_five_and_six_field_forms() {
+        let hourly = parse_cron("0 * * * *").unwrap();
+        let from = Local.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+        assert_eq!(
+            hourly.after(&from).next(),
+            Some(Local.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap())
+        );
+        assert!(parse_cron("30 17 * * Mon-Fri").is_ok());
+        assert!(parse_cron("0 */15 9-17 * * *").is_ok());
+    }
+
+    #[test]
+    fn parse_cron_rejects_invalid_expressions() {
+        for expr in ["", "nope", "61 * * * *", "* * *"] {
+            let error = parse_cron(expr).unwrap_err();
+            assert!(error.starts_with("Invalid cron expression"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn render_text_fills_placeholders() {
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 17, 30, 0).unwrap();
+        assert_eq!(
+            render_text(
+                "{{name}}: {{time}} | {{date}} | {{weekday}} | {{datetime}}",
+                "end-of-day",
+                &now
+            ),
+            "end-of-day: 5:30 PM | October 16, 2026 | Friday | Friday, October 16 at 5:30 PM"
+        );
+        assert_eq!(render_text("No placeholders", "x", &now), "No placeholders");
+    }
+
+    #[test]
+    fn compile_rejects_unknown_placeholders() {
+        let spec = |text: &str| ScheduleSpec {
+            name: "check-in".to_string(),
+            cron: "0 9 * * *".to_string(),
+            enabled: true,
+            request: PlayRequest {
+                text: text.to_string(),
+                ..Default::default()
+            },
+        };
+        assert!(Schedule::compile(spec("It is {{time}}")).is_ok());
+        assert!(Schedule::compile(spec("It is {{tiem}}")).is_err());
+        assert!(Schedule::compile(spec("It is {{time")).is_err());
+    }
+}
@@ -0,0 +1,536 @@
+//! Speech normalization: rewrite the Markdown, links, paths, IDs and stack traces that
+//! agents send into text that sounds sensible when read aloud
+
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+/// Spoken in place of common shorthand; `[speech.acronyms]` adds to and overrides these
+const ACRONYMS: &[(&str, &str)] = &[
+    ("k8s", "Kubernetes"),
+    ("PR", "pull request"),
+    ("PRs", "pull requests"),
+    ("DB", "database"),
+    ("OOM", "out of memory"),
+    ("prod", "production"),
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("w/", "with"),
+    ("w/o", "without"),
+];
+
+static FENCED_CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)```.*?```").unwrap());
+/// JavaScript/Java/.NET `at ...`, Python `File "...", line N`, Rust `N: path::fn` and
+/// gdb `#N 0x... in` frames, plus the headers and footers around them. An `at` line only
+/// counts when it names a method call or ends in a file location, so "at 14:00 the
+/// canary starts" stays.
+static STACK_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*(?:at\s+(?:[\w$<>]+\.[\w$.<>]+\(|.+?\s\(\S*:\d+(?::\d+)?\)\s*$|\S+:\d+:\d+\s*$)|File\s+".*",\s+line\s+\d+|\d+:\s+\S+::|#\d+\s+(?:0x[0-9a-fA-F]+|in\s)|Traceback \(most recent call last\)|stack backtrace:|\.\.\.\s*\d+\s+more)"#,
+    )
+    .unwrap()
+});
+static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)\s]+\)").unwrap());
+static ANGLE_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<(https?://[^<>\s]+)>").unwrap());
+/// The host is kept; the path, query and fragment are dropped
+static URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"https?://(?:www\.)?([A-Za-z0-9.-]*[A-Za-z0-9])(?::\d+)?(?:[/?#]\S*[^\s.,;:!?)'\x22])?/?",
+    )
+    .unwrap()
+});
+static INLINE_CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`\n]+)`").unwrap());
+static TABLE_RULE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*\|?(?:\s*:?-{3,}:?\s*\|)+\s*:?-*:?\s*$").unwrap());
+static HORIZONTAL_RULE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*(?:[-*_]\s*){3,}$").unwrap());
+static LINE_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*(?:>+\s*|#{1,6}\s+|[-*+•]\s+(?:\[[ xX]\]\s+)?)").unwrap()
+});
+/// Table cell separators
+static PIPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[ \t]*\|[ \t]*").unwrap());
+static FORMATTING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*+|~~").unwrap());
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\S+").unwrap());
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+        .unwrap()
+});
+static HASH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[0-9a-f]{7,}|[0-9A-F]{7,})$").unwrap());
+/// Absolute, home- or dot-relative, Windows, or two or more separators ending in a file
+/// name, with an optional `:line[:column]`
+static PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:(?:[A-Za-z]:\\|~?/|\.{1,2}/)(?:[\w.@+-]+[/\\])*[\w.@+-]*|(?:[\w.@+-]+[/\\]){2,}[\w@+-]*\.\w+)(?::(\d+)(?::\d+)?)?$",
+    )
+    .unwrap()
+});
+static CAMEL_BOUNDARY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([a-z0-9])([A-Z])|([A-Z])([A-Z][a-z])").unwrap());
+
+/// Rewrite `text` for the TTS provider according to `[speech]`. `toast` says whether the
+/// full text will also appear in a notification, for the note on truncated messages.
+pub fn normalize(text: &str, toast: bool) -> String {
+    let config = crate::config::get();
+    let speech = &config.speech;
+    if !speech.normalize {
+        return text.to_string();
+    }
+
+    let acronym = |word: &str| {
+        speech
+            .acronyms
+            .get(word)
+            .map(String::as_str)
+            .or_else(|| {
+                ACRONYMS
+                    .iter()
+                    .find(|(key, _)| *key == word)
+                    .map(|(_, spoken)| *spoken)
+            })
+            .map(String::from)
+    };
+
+    let text = FENCED_CODE.replace_all(text, "\nCode omitted.\n");
+    let text = without_stack_traces(&text);
+    let text = MARKDOWN_LINK.replace_all(&text, "$1");
+    let text = ANGLE_URL.replace_all(&text, "$1");
+    let text = URL.replace_all(&text, "$1");
+    let text = INLINE_CODE.replace_all(&text, |caps: &Captures| {
+        let code = caps[1].trim().trim_end_matches("()");
+        match speakable_token(code, &acronym) {
+            Some(spoken) => spoken,
+            None => CAMEL_BOUNDARY.replace_all(code, "$1$3 $2$4").into_owned(),
+        }
+    });
+    let text = TABLE_RULE.replace_all(&text, "");
+    let text = HORIZONTAL_RULE.replace_all(&text, "");
+    let text = LINE_PREFIX.replace_all(&text, "");
+    let text = FORMATTING.replace_all(&text, "");
+    let text = TOKEN.replace_all(&text, |caps: &Captures| {
+        speakable_token(&caps[0], &acronym).unwrap_or_else(|| caps[0].to_string())
+    });
+    // Underscores mark italics but also join words (snake_case) - either way a space reads better
+    let text = PIPE.replace_all(&text.replace('_', " "), ", ").into_owned();
+
+    // Each line becomes its own sentence so the voice pauses between them
+    let text = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .map(|line| line.trim_matches([',', ' ']).to_string())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.ends_with(['.', '!', '?', ':']) {
+                line
+            } else {
+                format!("{}.", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    truncate(&text, speech.max_chars, toast)
+}
+
+/// Replace each run of stack frames (and the indented source lines between them) with a
+/// short note, keeping the error message that precedes it. A single frame-like line is
+/// left alone.
+fn without_stack_traces(text: &str) -> String {
+    let input: Vec<&str> = text.lines().collect();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        if !STACK_FRAME.is_match(input[i]) {
+            lines.push(input[i]);
+            i += 1;
+            continue;
+        }
+
+        let mut end = i;
+        let mut frames = 0;
+        while end < input.len() {
+            if STACK_FRAME.is_match(input[end]) {
+                frames += 1;
+            } else if !input[end].starts_with([' ', '\t']) {
+                break;
+            }
+            end += 1;
+        }
+        if frames >= 2 {
+            lines.push("Stack trace omitted.");
+        } else {
+            lines.extend(&input[i..end]);
+        }
+        i = end;
+    }
+    lines.join("\n")
+}
+
+/// The spoken form of a whitespace-separated token, or None to leave it as it is
+fn speakable_token(token: &str, acronym: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(spoken) = acronym(token) {
+        return Some(spoken);
+    }
+
+    // Keep surrounding punctuation, so "(see /var/log/app.log)." stays a sentence
+    let start = token.find(|c: char| !"([{\"'".contains(c))?;
+    let end = token
+        .rfind(|c: char| !".,;:!?)]}\"'".contains(c))
+        .map(|i| i + token[i..].chars().next().map_or(1, char::len_utf8))?;
+    if start >= end {
+        return None;
+    }
+    let (prefix, core, suffix) = (&token[..start], &token[start..end], &token[end..]);
+
+    let spoken = if let Some(spoken) = acronym(core) {
+        spoken
+    } else if UUID.is_match(core) {
+        format!("ID ending in {}", spell(&core[core.len() - 4..]))
+    } else if HASH.is_match(core)
+        && core.contains(|c: char| c.is_ascii_digit())
+        && core.contains(|c: char| c.is_ascii_alphabetic())
+    {
+        format!("hash {}", spell(&core[..4]))
+    } else if let Some(caps) = PATH.captures(core) {
+        let path = match caps.get(1) {
+            Some(line) => &core[..line.start() - 1],
+            None => core,
+        };
+        let name = path
+            .trim_end_matches(['/', '\\'])
+            .rsplit(['/', '\\'])
+            .find(|part| !part.is_empty())?;
+        if !name.contains(|c: char| c.is_alphabetic()) {
+            return None;
+        }
+        match caps.get(1) {
+            Some(line) => format!("{} line {}", name, line.as_str()),
+            None => name.to_string(),
+        }
+    } else {
+        return None;
+    };
+
+    Some(format!("{}{}{}", prefix, spoken, suffix))
+}
+
+/// "4f2a" -> "4 f 2 a", so the voice reads characters instead of guessing at a word
+fn spell(text: &str) -> String {
+    text.chars()
+        .map(|c| c.to_lowercase().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Cut at the last sentence (or word) that fits in `max_chars`, and say so
+fn truncate(text: &str, max_chars: usize, toast: bool) -> String {
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let limit = text
+        .char_indices()
+        .nth(max_chars)
+        .map_or(text.len(), |(i, _)| i);
+    let head = &text[..limit];
+    let sentence_end = head
+        .rmatch_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&end| end > limit / 2 && text[end..].starts_with(' '));
+    let cut = match sentence_end {
+        Some(end) => &text[..end],
+        None => head
+            .rsplit_once(' ')
+            .map_or(head, |(words, _)| words)
+            .trim_end_matches([',', ';', ':']),
+    };
+
+    let note = if toast {
+        "Details in the notification."
+    } else {
+        "Message truncated."
+    };
+    if cut.ends_with(['.', '!', '?']) {
+        format!("{} {}", cut, note)
+    } else {
+        format!("{}... {}", cut, note)
+    }
+}
+
+/// The first chunk stops after its first sentence once it has this many characters, so
+/// playback can start on a short clip
+const FIRST_CHUNK_CHARS: usize = 60;
+
+/// Split `text` at sentence boundaries into chunks of at most `max_chars` (0 keeps it
+/// whole), cutting overlong sentences at a word. The first chunk is kept short so its
+/// audio is ready sooner.
+pub fn chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let text = text.trim();
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return vec![text.to_string()];
+    }
+
+    let mut sentences: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        if ".!?".contains(c) && text[end..].starts_with(char::is_whitespace) {
+            sentences.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim());
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for sentence in sentences.into_iter().filter(|s| !s.is_empty()) {
+        for piece in split_words(sentence, max_chars) {
+            let limit = if chunks.is_empty() {
+                FIRST_CHUNK_CHARS.min(max_chars)
+            } else {
+                max_chars
+            };
+            if !current.is_empty()
+                && (current.chars().count() >= limit
+                    || current.chars().count() + 1 + piece.chars().count() > max_chars)
+            {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Cut a sentence longer than `max_chars` at the last space that fits
+fn split_words(sentence: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = sentence;
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let cut = rest[..limit].rfind(' ').filter(|&i| i > 0).unwrap_or(limit);
+        pieces.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prose_that_looks_like_a_frame_is_kept() {
+        assert_eq!(
+            normalize("at 14:00 the canary starts\n  then prod", false),
+            "at 14:00 the canary starts. then production."
+        );
+        assert_eq!(
+            normalize("#1 priority: fix login", false),
+            "#1 priority: fix login."
+        );
+        assert_eq!(
+            without_stack_traces("Error: boom\n    at Foo.bar(Foo.java:3)\nDone"),
+            "Error: boom\n    at Foo.bar(Foo.java:3)\nDone"
+        );
+    }
+
+    #[test]
+    fn stack_traces_are_omitted() {
+        let java = "Error: boom\n\tat com.example.Foo.bar(Foo.java:3)\n\tat com.example.Main.main(Main.java:9)\nRetrying";
+        assert_eq!(
+            without_stack_traces(java),
+            "Error: boom\nStack trace omitted.\nRetrying"
+        );
+        let node = "TypeError: x\n    at async run (/app/index.js:4:7)\n    at /app/index.js:10:1";
+        assert_eq!(
+            without_stack_traces(node),
+            "TypeError: x\nStack trace omitted."
+        );
+        let python = "Traceback (most recent call last):\n  File \"app.py\", line 3, in <module>\n    main()\nValueError: bad";
+        assert_eq!(
+            without_stack_traces(python),
+            "Stack trace omitted.\nValueError: bad"
+        );
+        let gdb = "#0  0x00007f3a in raise () from libc.so\n#1  in main () at main.c:4";
+        assert_eq!(without_stack_traces(gdb), "Stack trace omitted.");
+    }
+
+    /// Chunks rejoined with spaces, to compare with the input
+    fn rejoined(text: &str, max_chars: usize) -> String {
+        let chunks = chunks(text, max_chars);
+        for chunk in &chunks {
+            assert!(
+                chunk.chars().count() <= max_chars,
+                "{:?} is longer than {}",
+                chunk,
+                max_chars
+            );
+        }
+        chunks.join(" ")
+    }
+
+    #[test]
+    fn chunks_split_at_sentences() {
+        let text = "First sentence here. Second one! Third one? And the last.";
+        assert_eq!(
+            chunks(text, 40),
+            [
+                "First sentence here. Second one!",
+                "Third one? And the last."
+            ]
+        );
+        assert_eq!(rejoined(text, 40), text);
+        assert_eq!(chunks("Short.", 40), ["Short."]);
+        assert_eq!(chunks(text, 0), [text]);
+    }
+
+    #[test]
+    fn first_chunk_stops_after_its_first_long_sentence() {
+        let first = "This opening sentence is long enough to be played on its own.";
+        let text = format!("{} Then a second. And a third.", first);
+        assert_eq!(chunks(&text, 80), [first, "Then a second. And a third."]);
+        // Text that fits stays whole
+        assert_eq!(chunks(&text, 250), [text.as_str()]);
+    }
+
+    #[test]
+    fn overlong_sentences_fall_back_to_words() {
+        let text = "one two three four five six seven eight nine ten";
+        assert_eq!(
+            chunks(text, 15),
+            ["one two three", "four five six", "seven eight", "nine ten"]
+        );
+        assert_eq!(rejoined(text, 15), text);
+        assert_eq!(split_words("alpha beta", 20), ["alpha beta"]);
+    }
+
+    #[test]
+    fn words_longer_than_the_limit_are_cut() {
+        let word = "supercalifragilisticexpialidocious";
+        assert_eq!(split_words(word, 10).concat(), word);
+        assert_eq!(chunks(word, 10).concat(), word);
+        assert_eq!(chunks(&format!("Hi. {}", word), 10)[0], "Hi.");
+    }
+
+    #[test]
+    fn chunks_respect_multibyte_characters() {
+        let text = "Grüße aus Köln. Ça va très bien! 日本語のテキストです。 Ünïcödé ëvérÿwhérë.";
+        for max_chars in [5, 8, 13, 21] {
+            assert_eq!(
+                rejoined(text, max_chars).replace(' ', ""),
+                text.replace(' ', "")
+            );
+        }
+        assert_eq!(split_words("日本語日本語", 4).concat(), "日本語日本語");
+    }
+
+    #[test]
+    fn normalize_markdown() {
+        for (input, expected) in [
+            (
+                "# Deploy done\n- **api** is up\n- ~~web~~ too",
+                "Deploy done. api is up. web too.",
+            ),
+            ("> quoted\n\n---\n1. first", "quoted. 1. first."),
+            ("| a | b |\n|---|---|\n| 1 | 2 |", "a, b. 1, 2."),
+            ("```\nlet x = 1;\n```\nDone", "Code omitted. Done."),
+            ("Run `cargo build` now", "Run cargo build now."),
+            ("- [x] tests pass", "tests pass."),
+        ] {
+            assert_eq!(normalize(input, false), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn normalize_links() {
+        for (input, expected) in [
+            ("See [the docs](https://example.com/docs)", "See the docs."),
+            ("![diagram](img.png) attached", "diagram attached."),
+            (
+                "Open https://www.github.com/org/repo/pull/42.",
+                "Open github.com.",
+            ),
+            ("Link: <https://example.com/a?b=c>", "Link: example.com."),
+            ("http://localhost:8080/health is down", "localhost is down."),
+        ] {
+            assert_eq!(normalize(input, false), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn normalize_paths() {
+        for (input, expected) in [
+            ("Edit /etc/nginx/nginx.conf", "Edit nginx.conf."),
+            (
+                "Failed at src/server/main.rs:42:7",
+                "Failed at main.rs line 42.",
+            ),
+            ("(see ~/logs/app.log).", "(see app.log)."),
+            ("Check C:\\Users\\me\\report.txt", "Check report.txt."),
+            ("Wrote ./out/", "Wrote out."),
+            ("Ratio 3/4", "Ratio 3/4."),
+        ] {
+            assert_eq!(normalize(input, false), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn normalize_identifiers() {
+        for (input, expected) in [
+            (
+                "Job 123e4567-e89b-12d3-a456-426614174000 failed",
+                "Job ID ending in 4 0 0 0 failed.",
+            ),
+            ("Commit 4f2a9c1e merged", "Commit hash 4 f 2 a merged."),
+            ("Call `getUserName()`", "Call get User Name."),
+            ("the retry_count setting", "the retry count setting."),
+            (
+                "k8s PR merged to prod",
+                "Kubernetes pull request merged to production.",
+            ),
+            ("deadbeef is a word", "deadbeef is a word."),
+        ] {
+            assert_eq!(normalize(input, false), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn truncate_respects_char_boundaries() {
+        assert_eq!(truncate("short", 10, false), "short");
+        assert_eq!(truncate("anything", 0, false), "anything");
+        assert_eq!(
+            truncate("First part. Second part is long.", 20, false),
+            "First part. Message truncated."
+        );
+        assert_eq!(
+            truncate("one two three four", 10, true),
+            "one two... Details in the notification."
+        );
+        // Multibyte characters right at the cut must not split
+        assert_eq!(
+            truncate("ééééé ééééé", 7, false),
+            "ééééé... Message truncated."
+        );
+        assert_eq!(
+            truncate("日本語テキスト", 3, false),
+            "日本語... Message truncated."
+        );
+        assert_eq!(
+            truncate("🚀🚀🚀 🚀🚀", 4, false),
+            "🚀🚀🚀... Message truncated."
+        );
+    }
+}
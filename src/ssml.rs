@@ -0,0 +1,600 @@
+//! SSML input: validate the subset callers may use, pass it to Edge inside its own
+//! `<speak>` document, and translate it to plain text plus speed and instructions for
+//! providers without SSML (OpenAI)
+
+use crate::TtsProvider;
+use crate::lexicon::{LexiconStore, escape_xml};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Elements callers may use. `<voice>` and `<mstts:*>` aren't allowed: the provider
+/// already wraps the input in a voice and doesn't declare the mstts namespace.
+const ELEMENTS: &[&str] = &[
+    "speak", "p", "s", "break", "emphasis", "say-as", "prosody", "sub", "phoneme",
+];
+
+static ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^&(?:amp|lt|gt|quot|apos|#\d+|#x[0-9a-fA-F]+);").unwrap());
+static NUMERIC_ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&#(x[0-9a-fA-F]+|\d+);").unwrap());
+static BREAK_TIME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+(?:\.\d+)?)\s*(ms|s)$").unwrap());
+
+enum Node<'a> {
+    /// An opening or self-closing tag, with the source text it came from
+    Open {
+        name: &'a str,
+        attrs: Vec<(&'a str, String)>,
+        empty: bool,
+        raw: &'a str,
+    },
+    Close {
+        name: &'a str,
+        raw: &'a str,
+    },
+    Text(&'a str),
+}
+
+/// Split SSML into tags and text, dropping the XML declaration and comments
+fn tokenize(input: &str) -> Result<Vec<Node<'_>>, String> {
+    let mut nodes = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            nodes.push(Node::Text(rest));
+            break;
+        };
+        if start > 0 {
+            nodes.push(Node::Text(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").ok_or("Unclosed comment in SSML")?;
+            rest = &rest[end + 3..];
+            continue;
+        }
+        if rest.starts_with("<?") {
+            let end = rest.find("?>").ok_or("Unclosed XML declaration in SSML")?;
+            rest = &rest[end + 2..];
+            continue;
+        }
+
+        let end = rest
+            .find('>')
+            .ok_or_else(|| format!("Unclosed tag in SSML: '{}'", truncated(rest)))?;
+        let raw = &rest[..=end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = raw.strip_prefix("</") {
+            let name = name.trim_end_matches('>').trim();
+            nodes.push(Node::Close { name, raw });
+            continue;
+        }
+
+        let inner = raw[1..raw.len() - 1].trim();
+        let (inner, empty) = match inner.strip_suffix('/') {
+            Some(inner) => (inner.trim_end(), true),
+            None => (inner, false),
+        };
+        let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+        let name = &inner[..name_end];
+        if name.is_empty() {
+            return Err(format!("Malformed tag in SSML: '{}'", raw));
+        }
+        let attrs = attributes(&inner[name_end..])
+            .ok_or_else(|| format!("Malformed attributes in SSML tag '{}'", raw))?;
+        nodes.push(Node::Open {
+            name,
+            attrs,
+            empty,
+            raw,
+        });
+    }
+
+    Ok(nodes)
+}
+
+/// Parse `name="value" name2='value'`, unescaping the values
+fn attributes(text: &str) -> Option<Vec<(&str, String)>> {
+    let mut attrs = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value_end = after[1..].find(quote)?;
+        attrs.push((name.trim(), unescape(&after[1..1 + value_end])));
+        rest = after[value_end + 2..].trim_start();
+    }
+    Some(attrs)
+}
+
+fn attr<'a>(attrs: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn unescape(text: &str) -> String {
+    let mut output = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'");
+    output = NUMERIC_ENTITY
+        .replace_all(&output, |caps: &regex::Captures| {
+            let code = &caps[1];
+            let value = match code.strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => code.parse().ok(),
+            };
+            value
+                .and_then(char::from_u32)
+                .map(String::from)
+                .unwrap_or_default()
+        })
+        .into_owned();
+    output.replace("&amp;", "&")
+}
+
+fn truncated(text: &str) -> String {
+    text.chars().take(40).collect()
+}
+
+/// Check that `input` is well-formed SSML using only the supported elements
+pub fn validate(input: &str) -> Result<(), String> {
+    let nodes = tokenize(input)?;
+    let mut open: Vec<&str> = Vec::new();
+    let mut has_text = false;
+
+    for node in &nodes {
+        match node {
+            Node::Open {
+                name, attrs, empty, ..
+            } => {
+                if !ELEMENTS.contains(name) {
+                    return Err(format!(
+                        "Unsupported SSML element <{}> (supported: {})",
+                        name,
+                        ELEMENTS.join(", ")
+                    ));
+                }
+                if *name == "speak" && !open.is_empty() {
+                    return Err("<speak> must be the outermost element".to_string());
+                }
+                check_attributes(name, attrs)?;
+                if !empty {
+                    if *name == "break" {
+                        return Err(
+                            "<break> must be empty, e.g. <break time=\"500ms\"/>".to_string()
+                        );
+                    }
+                    open.push(name);
+                }
+            }
+            Node::Close { name, .. } => match open.pop() {
+                Some(expected) if expected == *name => {}
+                Some(expected) => {
+                    return Err(format!(
+                        "Mismatched SSML tags: <{}> closed by </{}>",
+                        expected, name
+                    ));
+                }
+                None => return Err(format!("Unexpected closing tag </{}> in SSML", name)),
+            },
+            Node::Text(text) => {
+                for (i, _) in text.match_indices('&') {
+                    if !ENTITY.is_match(&text[i..]) {
+                        return Err(format!(
+                            "Unescaped '&' in SSML near '{}' (write &amp;)",
+                            truncated(&text[i..])
+                        ));
+                    }
+                }
+                has_text |= !text.trim().is_empty();
+            }
+        }
+    }
+
+    if let Some(name) = open.last() {
+        return Err(format!("Unclosed SSML element <{}>", name));
+    }
+    if !has_text {
+        return Err("SSML contains no text to speak".to_string());
+    }
+    Ok(())
+}
+
+fn check_attributes(name: &str, attrs: &[(&str, String)]) -> Result<(), String> {
+    match name {
+        "break" => {
+            if let Some(time) = attr(attrs, "time").filter(|time| !BREAK_TIME.is_match(time)) {
+                return Err(format!(
+                    "Invalid <break> time '{}' (expected e.g. 500ms or 2s)",
+                    time
+                ));
+            }
+            if let Some(strength) = attr(attrs, "strength").filter(|strength| {
+                !matches!(
+                    *strength,
+                    "none" | "x-weak" | "weak" | "medium" | "strong" | "x-strong"
+                )
+            }) {
+                return Err(format!("Invalid <break> strength '{}'", strength));
+            }
+        }
+        "prosody" => {
+            if let Some(rate) = attr(attrs, "rate").filter(|rate| rate_factor(rate).is_none()) {
+                return Err(format!(
+                    "Invalid <prosody> rate '{}' (expected x-slow to x-fast, a percentage or a multiplier)",
+                    rate
+                ));
+            }
+        }
+        "say-as" if attr(attrs, "interpret-as").is_none() => {
+            return Err("<say-as> needs an interpret-as attribute".to_string());
+        }
+        "sub" if attr(attrs, "alias").is_none() => {
+            return Err("<sub> needs an alias attribute".to_string());
+        }
+        "phoneme" if attr(attrs, "ph").is_none() => {
+            return Err("<phoneme> needs a ph attribute".to_string());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Relative speaking rate: a keyword, "+20%", or a multiplier like "1.2"
+fn rate_factor(rate: &str) -> Option<f32> {
+    let rate = rate.trim();
+    let factor = match rate {
+        "x-slow" => 0.5,
+        "slow" => 0.75,
+        "medium" | "default" => 1.0,
+        "fast" => 1.25,
+        "x-fast" => 1.75,
+        _ => match rate.strip_suffix('%') {
+            Some(percent) => 1.0 + percent.trim_start_matches('+').parse::<f32>().ok()? / 100.0,
+            None => rate.parse().ok()?,
+        },
+    };
+    (factor > 0.0).then_some(factor)
+}
+
+/// The input as a fragment for Edge's `<speak><voice><prosody>` document: the `<speak>`
+/// wrapper is dropped and the lexicon is applied to the text
+pub fn edge_fragment(input: &str, lexicon: &LexiconStore) -> Result<String, String> {
+    let mut output = String::new();
+    // Text inside these already says how it's pronounced
+    let mut pronounced = 0usize;
+
+    for node in tokenize(input)? {
+        match node {
+            Node::Open { name: "speak", .. } | Node::Close { name: "speak", .. } => {}
+            Node::Open {
+                name, empty, raw, ..
+            } => {
+                if !empty && matches!(name, "phoneme" | "sub" | "say-as") {
+                    pronounced += 1;
+                }
+                output.push_str(raw);
+            }
+            Node::Close { name, raw } => {
+                if matches!(name, "phoneme" | "sub" | "say-as") {
+                    pronounced = pronounced.saturating_sub(1);
+                }
+                output.push_str(raw);
+            }
+            Node::Text(text) if pronounced > 0 => output.push_str(&escape_xml(&unescape(text))),
+            Node::Text(text) => {
+                output.push_str(&lexicon.apply(&unescape(text), &TtsProvider::Edge))
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// SSML translated for a provider that only takes plain text
+pub struct Plain {
+    pub text: String,
+    /// Multiplier for the request's speed, from a `<prosody rate>` around everything
+    pub rate: Option<f32>,
+    /// Delivery hints for emphasis and partial prosody, for the instructions
+    pub hints: Vec<String>,
+}
+
+/// An element being translated, with the text spoken inside it so far
+struct Open<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, String)>,
+    start: usize,
+}
+
+/// Translate SSML to plain text: breaks become punctuation, `<sub>` its alias,
+/// `<say-as characters>` spelled-out letters, and emphasis and prosody delivery hints
+pub fn to_plain(input: &str) -> Result<Plain, String> {
+    let mut text = String::new();
+    let mut rate = None;
+    let mut hints = Vec::new();
+    let mut open: Vec<Open> = Vec::new();
+    // Closed prosody elements and where their text starts and ends
+    let mut prosodies: Vec<(Open, usize)> = Vec::new();
+
+    let nodes = tokenize(input)?;
+    for node in &nodes {
+        match node {
+            Node::Open {
+                name: "break",
+                attrs,
+                ..
+            } => {
+                let kept = pause(&mut text, break_punctuation(attrs));
+                clamp_offsets(&mut open, &mut prosodies, kept);
+            }
+            Node::Open {
+                name, attrs, empty, ..
+            } => {
+                // Paragraphs and sentences start after a full stop
+                if matches!(*name, "p" | "s") {
+                    let kept = pause(&mut text, ".");
+                    clamp_offsets(&mut open, &mut prosodies, kept);
+                }
+                if let Some(alias) = attr(attrs, "alias").filter(|_| *name == "sub") {
+                    text.push_str(alias);
+                }
+                if !empty {
+                    open.push(Open {
+                        name,
+                        attrs: attrs.clone(),
+                        start: text.len(),
+                    });
+                }
+            }
+            Node::Close { .. } => {
+                let Some(element) = open.pop() else {
+                    continue;
+                };
+                let inner = spoken_inside(&text[element.start..]).to_string();
+                let emphasized = attr(&element.attrs, "level") != Some("reduced");
+                match element.name {
+                    "p" | "s" => {
+                        let kept = pause(&mut text, ".");
+                        clamp_offsets(&mut open, &mut prosodies, kept);
+                    }
+                    "emphasis" if emphasized && !inner.is_empty() => {
+                        hints.push(format!("Emphasize \"{}\".", inner));
+                    }
+                    "prosody" => prosodies.push((element, text.len())),
+                    _ => {}
+                }
+            }
+            Node::Text(raw) => {
+                if open.iter().any(|e| e.name == "sub") {
+                    continue;
+                }
+                let spoken = unescape(raw);
+                let spell = open.iter().any(|e| {
+                    e.name == "say-as"
+                        && matches!(
+                            attr(&e.attrs, "interpret-as"),
+                            Some("characters" | "spell-out" | "letters")
+                        )
+                });
+                if spell {
+                    let letters: Vec<String> = spoken
+                        .chars()
+                        .filter(|c| !c.is_whitespace())
+                        .map(String::from)
+                        .collect();
+                    text.push_str(&letters.join(" "));
+                } else {
+                    text.push_str(&spoken);
+                }
+            }
+        }
+    }
+
+    // A prosody around all of the text sets the whole request's speed and delivery; one
+    // around part of it can only be described in the instructions
+    for (element, end) in prosodies {
+        let factor = attr(&element.attrs, "rate").and_then(rate_factor);
+        let mut delivery = prosody_delivery(&element.attrs);
+        let inner = spoken_inside(&text[element.start..end]);
+        let silent = |part: &str| {
+            part.chars()
+                .all(|c| c.is_whitespace() || ".,;:!?".contains(c))
+        };
+        if silent(&text[..element.start]) && silent(&text[end..]) {
+            rate = factor.filter(|factor| *factor != 1.0);
+            if !delivery.is_empty() {
+                hints.push(format!("Speak {}.", delivery.join(" and ")));
+            }
+        } else if !inner.is_empty() {
+            if let Some(pace) = factor.and_then(pace) {
+                delivery.insert(0, pace.to_string());
+            }
+            if !delivery.is_empty() {
+                hints.push(format!("Say \"{}\" {}.", inner, delivery.join(" and ")));
+            }
+        }
+    }
+
+    Ok(Plain {
+        text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        rate,
+        hints,
+    })
+}
+
+/// End the text so far with punctuation for a pause, unless it already has some.
+/// Returns how many bytes of the text were kept, as trailing spaces and punctuation may
+/// be replaced.
+fn pause(text: &mut String, punctuation: &str) -> usize {
+    if punctuation.is_empty() {
+        return text.len();
+    }
+    let trimmed = text.trim_end();
+    if trimmed.is_empty() {
+        return text.len();
+    }
+    let kept = if punctuation == "..." {
+        trimmed
+            .trim_end_matches(['.', ',', ';', ':', '!', '?'])
+            .len()
+    } else {
+        trimmed.len()
+    };
+    let ends_with_punctuation = trimmed.ends_with(['.', ',', ';', ':', '!', '?']);
+    text.truncate(kept);
+    if punctuation == "..." || !ends_with_punctuation {
+        text.push_str(punctuation);
+    }
+    text.push(' ');
+    kept
+}
+
+/// An element's text without the pause before it, which may start inside it
+fn spoken_inside(text: &str) -> &str {
+    text.trim_start_matches(|c: char| c.is_whitespace() || ".,;:!?".contains(c))
+        .trim_end()
+}
+
+/// Keep element offsets inside the text after a pause cut it back to `kept` bytes
+fn clamp_offsets(open: &mut [Open], prosodies: &mut [(Open, usize)], kept: usize) {
+    for element in open.iter_mut() {
+        element.start = element.start.min(kept);
+    }
+    for (element, end) in prosodies.iter_mut() {
+        element.start = element.start.min(kept);
+        *end = (*end).min(kept);
+    }
+}
+
+/// Longer breaks get stronger punctuation
+fn break_punctuation(attrs: &[(&str, String)]) -> &'static str {
+    if let Some(caps) = attr(attrs, "time").and_then(|time| BREAK_TIME.captures(time)) {
+        let amount: f64 = caps[1].parse().unwrap_or(0.0);
+        let ms = if &caps[2] == "s" {
+            amount * 1000.0
+        } else {
+            amount
+        };
+        return match ms {
+            ms if ms <= 0.0 => "",
+            ms if ms < 500.0 => ",",
+            ms if ms < 1500.0 => ".",
+            _ => "...",
+        };
+    }
+    match attr(attrs, "strength").unwrap_or("medium") {
+        "none" => "",
+        "x-weak" | "weak" | "medium" => ",",
+        "strong" => ".",
+        _ => "...",
+    }
+}
+
+fn pace(factor: f32) -> Option<&'static str> {
+    if factor < 1.0 {
+        Some("slowly")
+    } else if factor > 1.0 {
+        Some("quickly")
+    } else {
+        None
+    }
+}
+
+/// Pitch and volume as delivery words
+fn prosody_delivery(attrs: &[(&str, String)]) -> Vec<String> {
+    let direction = |value: &str, low: &[&str], high: &[&str]| {
+        if low.contains(&value) || value.starts_with('-') {
+            Some(false)
+        } else if high.contains(&value) || value.starts_with('+') {
+            Some(true)
+        } else {
+            None
+        }
+    };
+
+    let mut delivery = Vec::new();
+    match attr(attrs, "pitch")
+        .and_then(|pitch| direction(pitch, &["x-low", "low"], &["high", "x-high"]))
+    {
+        Some(true) => delivery.push("with a higher pitch".to_string()),
+        Some(false) => delivery.push("with a lower pitch".to_string()),
+        None => {}
+    }
+    match attr(attrs, "volume")
+        .and_then(|volume| direction(volume, &["silent", "x-soft", "soft"], &["loud", "x-loud"]))
+    {
+        Some(true) => delivery.push("loudly".to_string()),
+        Some(false) => delivery.push("softly".to_string()),
+        None => {}
+    }
+    delivery
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(input: &str) -> String {
+        validate(input).unwrap();
+        to_plain(input).unwrap().text
+    }
+
+    #[test]
+    fn break_inside_an_element_after_trailing_whitespace() {
+        let input = "<speak>\n  Alert!\n  <emphasis><break time=\"1s\"/>a</emphasis>\n</speak>";
+        let result = to_plain(input).unwrap();
+        assert_eq!(result.text, "Alert! a");
+        assert_eq!(result.hints, ["Emphasize \"a\"."]);
+    }
+
+    #[test]
+    fn long_break_replaces_punctuation_inside_an_element() {
+        let input =
+            "<speak>Wait. <prosody rate=\"fast\">  <break time=\"2s\"/>go</prosody> now</speak>";
+        let result = to_plain(input).unwrap();
+        assert_eq!(result.text, "Wait... go now");
+        assert_eq!(result.hints, ["Say \"go\" quickly."]);
+    }
+
+    #[test]
+    fn paragraphs_and_sentences_inside_open_elements() {
+        assert_eq!(
+            plain("<speak><emphasis>One  <p>two</p>  <s>three</s></emphasis> four</speak>"),
+            "One. two. three. four"
+        );
+        assert_eq!(
+            plain("<speak><prosody volume=\"loud\">First,\n<s> second</s>\t</prosody></speak>"),
+            "First, second."
+        );
+    }
+
+    #[test]
+    fn prosody_around_everything_sets_the_rate() {
+        let result =
+            to_plain("<speak><prosody rate=\"slow\">Hello there. </prosody><break/></speak>")
+                .unwrap();
+        assert_eq!(result.text, "Hello there.");
+        assert_eq!(result.rate, Some(0.75));
+    }
+
+    #[test]
+    fn breaks_become_punctuation() {
+        assert_eq!(plain("<speak>a<break time=\"200ms\"/>b</speak>"), "a, b");
+        assert_eq!(
+            plain("<speak>a <break strength=\"strong\"/> b</speak>"),
+            "a. b"
+        );
+        assert_eq!(plain("<speak>a!<break time=\"3s\"/>b</speak>"), "a... b");
+        assert_eq!(plain("<speak>a?<break time=\"1s\"/>b</speak>"), "a? b");
+        assert_eq!(plain("<speak><break time=\"1s\"/>a</speak>"), "a");
+    }
+}
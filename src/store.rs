@@ -0,0 +1,439 @@
+//! SQLite persistence for the transmission queue and history
+
+use crate::{AppState, TransmissionRequest, TtsProvider};
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, params, params_from_iter, types::Value};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+/// Local SQLite database shared by the API handlers and the queue processor
+pub struct Store {
+    conn: Mutex<Connection>,
+    /// Maximum number of synthesized clips kept in the audio cache (0 disables caching)
+    audio_cache_size: usize,
+}
+
+impl Store {
+    /// Open the database at `server.database_path`, falling back to an in-memory
+    /// database so the service still runs if the file is unusable
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        let path = &config.server.database_path;
+
+        let conn = match Connection::open(path) {
+            Ok(conn) => {
+                info!("Transmission database: {}", path);
+                conn
+            }
+            Err(e) => {
+                error!("Failed to open database {}: {}", path, e);
+                warn!("Falling back to in-memory storage - queue will not survive restarts");
+                Connection::open_in_memory().expect("Failed to open in-memory database")
+            }
+        };
+
+        let store = Store {
+            conn: Mutex::new(conn),
+            audio_cache_size: config.tts.audio_cache_size,
+        };
+        if let Err(e) = store.migrate() {
+            error!("Failed to initialize database schema: {}", e);
+        }
+        store
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS queue (
+                    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                    id TEXT NOT NULL UNIQUE,
+                    payload TEXT NOT NULL,
+                    queued_at INTEGER NOT NULL,
+                    in_flight INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE TABLE IF NOT EXISTS history (
+                    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                    id TEXT NOT NULL,
+                    text TEXT NOT NULL,
+                    voice TEXT NOT NULL,
+                    provider TEXT NOT NULL,
+                    tone TEXT NOT NULL,
+                    toast_urgency TEXT NOT NULL,
+                    client TEXT,
+                    queued_at TEXT NOT NULL,
+                    started_at TEXT NOT NULL,
+                    finished_at TEXT NOT NULL,
+                    duration_ms INTEGER NOT NULL,
+                    status TEXT NOT NULL,
+                    error TEXT,
+                    payload TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS history_queued_at ON history (queued_at);
+                CREATE TABLE IF NOT EXISTS audio_cache (
+                    key TEXT PRIMARY KEY,
+                    audio BLOB NOT NULL,
+                    last_used INTEGER NOT NULL
+                );",
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Record a transmission as pending
+    pub fn enqueue(&self, req: &TransmissionRequest) -> Result<(), String> {
+        let payload = serde_json::to_string(req).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO queue (id, payload, queued_at) VALUES (?1, ?2, ?3)",
+                params![req.id, payload, req.queued_at.timestamp()],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Failed to persist transmission {}: {}", req.id, e))
+    }
+
+    /// Mark a transmission as currently being processed
+    pub fn mark_in_flight(&self, id: &str) {
+        if let Err(e) = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE queue SET in_flight = 1 WHERE id = ?1", params![id])
+        {
+            error!(id, "Failed to mark transmission in flight: {}", e);
+        }
+    }
+
+    /// Remove a transmission once it has finished (successfully or not)
+    pub fn remove(&self, id: &str) {
+        if let Err(e) = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM queue WHERE id = ?1", params![id])
+        {
+            error!(id, "Failed to remove transmission from queue: {}", e);
+        }
+    }
+
+    /// Load transmissions left over from a previous run, in queue order.
+    /// Items older than `ttl` are deleted instead of returned.
+    pub fn restore(
+        &self,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<(Vec<TransmissionRequest>, usize), String> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut dropped = 0;
+        if let Some(ttl) = ttl {
+            let cutoff: DateTime<Utc> = Utc::now() - ttl;
+            dropped = conn
+                .execute(
+                    "DELETE FROM queue WHERE queued_at < ?1",
+                    params![cutoff.timestamp()],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT id, payload FROM queue ORDER BY seq")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut restored = Vec::new();
+        for row in rows {
+            let (id, payload) = row.map_err(|e| e.to_string())?;
+            match serde_json::from_str::<TransmissionRequest>(&payload) {
+                Ok(req) => restored.push(req),
+                Err(e) => {
+                    warn!(id = %id, "Discarding unreadable queued transmission: {}", e);
+                    dropped += 1;
+                    conn.execute("DELETE FROM queue WHERE id = ?1", params![id])
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+
+        Ok((restored, dropped))
+    }
+}
+
+/// Timestamps are stored as fixed-width UTC RFC 3339 so they sort and compare as text
+fn timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// One spoken (or attempted) transmission
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub text: String,
+    pub voice: String,
+    pub provider: String,
+    pub tone: String,
+    pub toast_urgency: String,
+    pub client: Option<String>,
+    pub queued_at: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: i64,
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// GET /history query parameters
+#[derive(Deserialize, Default)]
+pub struct HistoryQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    /// `completed`, `failed` or `cancelled`
+    pub status: Option<String>,
+    pub voice: Option<String>,
+    pub provider: Option<String>,
+    pub tone: Option<String>,
+    pub urgency: Option<String>,
+    /// Substring match on the requesting client
+    pub client: Option<String>,
+    /// Substring match on the spoken text
+    pub q: Option<String>,
+    /// Only entries queued at or after this RFC 3339 timestamp
+    pub since: Option<String>,
+    /// Only entries queued before this RFC 3339 timestamp
+    pub until: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryPage {
+    pub total: i64,
+    pub limit: u32,
+    pub offset: u32,
+    pub items: Vec<HistoryEntry>,
+}
+
+const DEFAULT_HISTORY_LIMIT: u32 = 50;
+const MAX_HISTORY_LIMIT: u32 = 500;
+
+impl Store {
+    /// Record the outcome of a processed transmission
+    pub fn record_history(
+        &self,
+        req: &TransmissionRequest,
+        provider: &TtsProvider,
+        started_at: DateTime<Utc>,
+        finished_at: DateTime<Utc>,
+        status: &str,
+        error: Option<String>,
+    ) {
+        let payload = serde_json::to_string(req).unwrap_or_default();
+
+        if let Err(e) = self.conn.lock().unwrap().execute(
+            "INSERT INTO history (id, text, voice, provider, tone, toast_urgency, client,
+                queued_at, started_at, finished_at, duration_ms, status, error, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                req.id,
+                req.text,
+                req.voice,
+                provider.as_str(),
+                req.tone_type.as_str(),
+                req.toast_urgency.as_str(),
+                req.client,
+                timestamp(&req.queued_at),
+                timestamp(&started_at),
+                timestamp(&finished_at),
+                (finished_at - started_at).num_milliseconds(),
+                status,
+                error,
+                payload,
+            ],
+        ) {
+            error!(id = %req.id, "Failed to record history: {}", e);
+        }
+    }
+
+    /// Load the full transmission for a history entry, or the most recent one if `id` is None
+    pub fn history_transmission(&self, id: Option<&str>) -> Option<TransmissionRequest> {
+        let conn = self.conn.lock().unwrap();
+        let payload: Result<String, _> = match id {
+            Some(id) => conn.query_row(
+                "SELECT payload FROM history WHERE id = ?1 ORDER BY seq DESC LIMIT 1",
+                params![id],
+                |row| row.get(0),
+            ),
+            None => conn.query_row(
+                "SELECT payload FROM history ORDER BY seq DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            ),
+        };
+        serde_json::from_str(&payload.ok()?).ok()
+    }
+
+    /// Look up previously synthesized audio
+    pub fn cached_audio(&self, key: &str) -> Option<Vec<u8>> {
+        if self.audio_cache_size == 0 {
+            return None;
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let audio: Vec<u8> = conn
+            .query_row(
+                "SELECT audio FROM audio_cache WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .ok()?;
+        let _ = conn.execute(
+            "UPDATE audio_cache SET last_used = ?1 WHERE key = ?2",
+            params![Utc::now().timestamp_millis(), key],
+        );
+        Some(audio)
+    }
+
+    /// Store synthesized audio, evicting the least recently used clips beyond the cache size
+    pub fn cache_audio(&self, key: &str, audio: &[u8]) {
+        if self.audio_cache_size == 0 {
+            return;
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let result = conn
+            .execute(
+                "INSERT OR REPLACE INTO audio_cache (key, audio, last_used) VALUES (?1, ?2, ?3)",
+                params![key, audio, Utc::now().timestamp_millis()],
+            )
+            .and_then(|_| {
+                conn.execute(
+                    "DELETE FROM audio_cache WHERE key NOT IN
+                        (SELECT key FROM audio_cache ORDER BY last_used DESC LIMIT ?1)",
+                    params![self.audio_cache_size as i64],
+                )
+            });
+        if let Err(e) = result {
+            error!("Failed to cache audio: {}", e);
+        }
+    }
+
+    /// Query history, newest first
+    pub fn history(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+
+        let exact = [
+            ("status = ?", &query.status),
+            ("voice = ?", &query.voice),
+            ("provider = upper(?)", &query.provider),
+            ("tone = upper(?)", &query.tone),
+            ("toast_urgency = lower(?)", &query.urgency),
+        ];
+        for (clause, value) in exact {
+            if let Some(value) = value {
+                clauses.push(clause);
+                values.push(Value::Text(value.clone()));
+            }
+        }
+
+        let contains = [("client LIKE ?", &query.client), ("text LIKE ?", &query.q)];
+        for (clause, value) in contains {
+            if let Some(value) = value {
+                clauses.push(clause);
+                values.push(Value::Text(format!("%{}%", value)));
+            }
+        }
+
+        for (clause, value) in [
+            ("queued_at >= ?", &query.since),
+            ("queued_at < ?", &query.until),
+        ] {
+            if let Some(value) = value {
+                let parsed = DateTime::parse_from_rfc3339(value)
+                    .map_err(|e| format!("Invalid timestamp '{}': {}", value, e))?;
+                clauses.push(clause);
+                values.push(Value::Text(timestamp(&parsed.with_timezone(&Utc))));
+            }
+        }
+
+        let where_sql = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_HISTORY_LIMIT)
+            .clamp(1, MAX_HISTORY_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+
+        let conn = self.conn.lock().unwrap();
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM history {}", where_sql),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, text, voice, provider, tone, toast_urgency, client, queued_at,
+                    started_at, finished_at, duration_ms, status, error
+                 FROM history {} ORDER BY seq DESC LIMIT {} OFFSET {}",
+                where_sql, limit, offset
+            ))
+            .map_err(|e| e.to_string())?;
+
+        let items = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(HistoryEntry {
+                    id: row.get(0)?,
+                    text: row.get(1)?,
+                    voice: row.get(2)?,
+                    provider: row.get(3)?,
+                    tone: row.get(4)?,
+                    toast_urgency: row.get(5)?,
+                    client: row.get(6)?,
+                    queued_at: row.get(7)?,
+                    started_at: row.get(8)?,
+                    finished_at: row.get(9)?,
+                    duration_ms: row.get(10)?,
+                    status: row.get(11)?,
+                    error: row.get(12)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(HistoryPage {
+            total,
+            limit,
+            offset,
+            items,
+        })
+    }
+}
+
+/// GET /history - paginated, filterable record of past transmissions
+pub async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    match state.store.history(&query) {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("Error: {}", e)).into_response(),
+    }
+}
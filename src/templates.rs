@@ -0,0 +1,467 @@
+//! Server-side message templates: named texts in the config with `{{var}}` placeholders,
+//! filled from a request's `vars` and formatted so numbers and times read naturally aloud
+
+use axum::{extract::Json, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// A `{{name | helper:arg | ...}}` placeholder
+struct Placeholder<'a> {
+    name: &'a str,
+    helpers: Vec<(&'a str, Option<&'a str>)>,
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Var(Placeholder<'a>),
+}
+
+const HELPERS: &[&str] = &[
+    "duration", "number", "percent", "relative", "plural", "default",
+];
+
+fn parse(template: &str) -> Result<Vec<Part<'_>>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| format!("Unclosed placeholder in template '{}'", template))?;
+
+        let mut pieces = after[..end].split('|').map(str::trim);
+        let name = pieces.next().unwrap_or_default();
+        if name.is_empty() {
+            return Err(format!("Empty placeholder in template '{}'", template));
+        }
+        let mut helpers = Vec::new();
+        for piece in pieces {
+            let (helper, arg) = match piece.split_once(':') {
+                Some((helper, arg)) => (helper.trim(), Some(arg.trim())),
+                None => (piece, None),
+            };
+            if !HELPERS.contains(&helper) {
+                return Err(format!(
+                    "Unknown helper '{}' in template '{}' (expected {})",
+                    helper,
+                    template,
+                    HELPERS.join(", ")
+                ));
+            }
+            check_arg(helper, arg)?;
+            helpers.push((helper, arg));
+        }
+        parts.push(Part::Var(Placeholder { name, helpers }));
+
+        rest = &after[end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+
+    Ok(parts)
+}
+
+fn check_arg(helper: &str, arg: Option<&str>) -> Result<(), String> {
+    match (helper, arg) {
+        ("duration", Some(unit)) if !matches!(unit, "ms" | "s" | "m" | "h") => Err(format!(
+            "duration unit must be ms, s, m or h, not '{}'",
+            unit
+        )),
+        ("number" | "percent", Some(decimals)) if decimals.parse::<usize>().is_err() => {
+            Err(format!(
+                "{} takes a number of decimal places, not '{}'",
+                helper, decimals
+            ))
+        }
+        ("plural", None) => Err("plural needs a noun, e.g. plural:failure".to_string()),
+        ("default", None) => Err("default needs fallback text, e.g. default:unknown".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Check a template's syntax, for config validation
+pub fn validate(template: &str) -> Result<(), String> {
+    parse(template).map(|_| ())
+}
+
+/// Variables a template uses, in order of first use
+pub fn variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let placeholders =
+        parse(template)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|part| match part {
+                Part::Var(placeholder) => Some(placeholder.name),
+                Part::Text(_) => None,
+            });
+    for name in placeholders {
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Render the named template from the config with `vars`, reporting every missing variable
+pub fn render(name: &str, vars: &Map<String, Value>) -> Result<String, String> {
+    let config = crate::config::get();
+    let template = config.templates.get(name).ok_or_else(|| {
+        let available: Vec<&str> = config.templates.keys().map(String::as_str).collect();
+        if available.is_empty() {
+            format!("Unknown template '{}' (no templates are configured)", name)
+        } else {
+            format!(
+                "Unknown template '{}' (available: {})",
+                name,
+                available.join(", ")
+            )
+        }
+    })?;
+
+    let (output, missing) = fill(template, |name| Ok(vars.get(name).cloned()))?;
+    if !missing.is_empty() {
+        return Err(format!(
+            "Template '{}' is missing variable(s): {}",
+            name,
+            missing.join(", ")
+        ));
+    }
+
+    Ok(output.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Fill a template's placeholders with values from `lookup` and apply their helpers, in one
+/// pass so `{{...}}` inside a value is kept as text. Placeholders with no value and no
+/// default are left empty and returned as missing.
+pub fn fill(
+    template: &str,
+    lookup: impl Fn(&str) -> Result<Option<Value>, String>,
+) -> Result<(String, Vec<&str>), String> {
+    let mut output = String::new();
+    let mut missing: Vec<&str> = Vec::new();
+    for part in parse(template)? {
+        match part {
+            Part::Text(text) => output.push_str(text),
+            Part::Var(placeholder) => {
+                let value = lookup(placeholder.name)?
+                    .filter(|value| !value.is_null() && value.as_str() != Some(""));
+                match format(placeholder.name, value.as_ref(), &placeholder.helpers)? {
+                    Some(text) => output.push_str(&text),
+                    None if !missing.contains(&placeholder.name) => missing.push(placeholder.name),
+                    None => {}
+                }
+            }
+        }
+    }
+    Ok((output, missing))
+}
+
+/// Apply a placeholder's helpers in order. None means the variable is missing with no default.
+fn format(
+    name: &str,
+    value: Option<&Value>,
+    helpers: &[(&str, Option<&str>)],
+) -> Result<Option<String>, String> {
+    let Some(value) = value else {
+        return Ok(helpers
+            .iter()
+            .find(|(helper, _)| *helper == "default")
+            .and_then(|(_, arg)| arg.map(str::to_string)));
+    };
+
+    let mut text = plain(value);
+    for (helper, arg) in helpers {
+        let number = || {
+            text.trim().parse::<f64>().map_err(|_| {
+                format!(
+                    "Variable '{}' must be a number for {}, got '{}'",
+                    name, helper, text
+                )
+            })
+        };
+        text = match *helper {
+            "duration" => {
+                let seconds = match arg.unwrap_or("s") {
+                    "ms" => number()? / 1000.0,
+                    "m" => number()? * 60.0,
+                    "h" => number()? * 3600.0,
+                    _ => number()?,
+                };
+                duration(seconds)
+            }
+            "number" => format_number(number()?, decimals(*arg)),
+            "percent" => format!("{} percent", format_number(number()?, decimals(*arg))),
+            "relative" => {
+                let at = timestamp(&text).ok_or_else(|| {
+                    format!(
+                        "Variable '{}' must be an RFC 3339 or Unix timestamp for relative, got '{}'",
+                        name, text
+                    )
+                })?;
+                relative(at, Utc::now())
+            }
+            "plural" => {
+                let count = number()?;
+                let noun = arg.unwrap_or_default();
+                format!("{} {}", format_number(count, 2), plural(noun, count))
+            }
+            _ => text,
+        };
+    }
+    Ok(Some(text))
+}
+
+/// Strings as-is, lists read as "a, b and c"
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .filter(|item| !item.is_null())
+                .map(plain)
+                .collect();
+            match items.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    format!("{} and {}", rest.join(", "), last)
+                }
+                Some((last, _)) => last.clone(),
+                None => String::new(),
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+fn decimals(arg: Option<&str>) -> usize {
+    arg.and_then(|arg| arg.parse().ok()).unwrap_or(2)
+}
+
+/// Round to `decimals` places, drop trailing zeros and group thousands: 12345.50 -> "12,345.5"
+fn format_number(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = match formatted.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction.trim_end_matches('0')),
+        None => (formatted.as_str(), ""),
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if !fraction.is_empty() {
+        grouped = format!("{}.{}", grouped, fraction);
+    }
+    if value < 0.0 && grouped.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        grouped.insert(0, '-');
+    }
+    grouped
+}
+
+fn plural(noun: &str, count: f64) -> String {
+    // "person,people" for irregular nouns, otherwise add an s
+    let (singular, plural) = match noun.split_once(',') {
+        Some((singular, plural)) => (singular.trim().to_string(), plural.trim().to_string()),
+        None => (noun.to_string(), format!("{}s", noun)),
+    };
+    if count == 1.0 { singular } else { plural }
+}
+
+fn unit(count: i64, name: &str) -> String {
+    format!("{} {}{}", count, name, if count == 1 { "" } else { "s" })
+}
+
+/// The two largest units: 3725 -> "1 hour and 2 minutes"
+fn duration(seconds: f64) -> String {
+    let total = seconds.abs().round() as i64;
+    if total == 0 {
+        return if seconds == 0.0 {
+            "0 seconds".to_string()
+        } else {
+            "less than a second".to_string()
+        };
+    }
+
+    let units = [
+        (total / 86400, "day"),
+        (total % 86400 / 3600, "hour"),
+        (total % 3600 / 60, "minute"),
+        (total % 60, "second"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(count, _)| *count == 0)
+        .take(2)
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| unit(*count, name))
+        .collect();
+    parts.join(" and ")
+}
+
+/// RFC 3339, or Unix seconds (milliseconds if implausibly large)
+fn timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let value: f64 = text.parse().ok()?;
+    let millis = if value.abs() > 1e11 {
+        value
+    } else {
+        value * 1000.0
+    };
+    DateTime::from_timestamp_millis(millis as i64)
+}
+
+/// "just now", "5 minutes ago", "in 2 hours"
+fn relative(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - at).num_seconds();
+    let magnitude = seconds.abs();
+    if magnitude < 45 {
+        return "just now".to_string();
+    }
+
+    // Round first, then pick the unit, so 3590 seconds is "1 hour" rather than "60 minutes"
+    let minutes = (magnitude as f64 / 60.0).round().max(1.0) as i64;
+    let hours = (magnitude as f64 / 3600.0).round() as i64;
+    let amount = if minutes < 60 {
+        unit(minutes, "minute")
+    } else if hours < 24 {
+        unit(hours, "hour")
+    } else {
+        unit((magnitude as f64 / 86400.0).round().max(1.0) as i64, "day")
+    };
+    if seconds > 0 {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+#[derive(Serialize)]
+pub struct TemplateInfo {
+    pub template: String,
+    pub variables: Vec<String>,
+}
+
+/// GET /templates - configured templates with the variables each one uses
+pub async fn templates_handler() -> impl IntoResponse {
+    let templates: BTreeMap<String, TemplateInfo> = crate::config::get()
+        .templates
+        .iter()
+        .map(|(name, template)| {
+            let info = TemplateInfo {
+                template: template.clone(),
+                variables: variables(template),
+            };
+            (name.clone(), info)
+        })
+        .collect();
+    Json(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn ago(seconds: i64) -> String {
+        let now = Utc::now();
+        relative(now - Duration::seconds(seconds), now)
+    }
+
+    #[test]
+    fn format_number_groups_and_trims() {
+        assert_eq!(format_number(12345.5, 2), "12,345.5");
+        assert_eq!(format_number(1000.0, 0), "1,000");
+        assert_eq!(format_number(999.999, 2), "1,000");
+        assert_eq!(format_number(1234567.0, 2), "1,234,567");
+        assert_eq!(format_number(0.25, 2), "0.25");
+        assert_eq!(format_number(-1234.567, 2), "-1,234.57");
+        assert_eq!(format_number(-0.001, 2), "0");
+        assert_eq!(format_number(42.0, 0), "42");
+    }
+
+    #[test]
+    fn duration_uses_the_two_largest_units() {
+        assert_eq!(duration(0.0), "0 seconds");
+        assert_eq!(duration(0.2), "less than a second");
+        assert_eq!(duration(1.0), "1 second");
+        assert_eq!(duration(59.6), "1 minute");
+        assert_eq!(duration(3725.0), "1 hour and 2 minutes");
+        assert_eq!(duration(3599.0), "59 minutes and 59 seconds");
+        assert_eq!(duration(86405.0), "1 day");
+        assert_eq!(duration(90061.0), "1 day and 1 hour");
+        assert_eq!(duration(-120.0), "2 minutes");
+    }
+
+    #[test]
+    fn relative_rounds_before_choosing_the_unit() {
+        assert_eq!(ago(10), "just now");
+        assert_eq!(ago(-44), "just now");
+        assert_eq!(ago(45), "1 minute ago");
+        assert_eq!(ago(3500), "58 minutes ago");
+        assert_eq!(ago(3570), "1 hour ago");
+        assert_eq!(ago(3599), "1 hour ago");
+        assert_eq!(ago(-7200), "in 2 hours");
+        assert_eq!(ago(84600), "1 day ago");
+        assert_eq!(ago(86399), "1 day ago");
+        assert_eq!(ago(3 * 86400), "3 days ago");
+    }
+
+    #[test]
+    fn plural_picks_the_form_for_the_count() {
+        assert_eq!(plural("failure", 1.0), "failure");
+        assert_eq!(plural("failure", 0.0), "failures");
+        assert_eq!(plural("failure", 2.0), "failures");
+        assert_eq!(plural("failure", 1.5), "failures");
+        assert_eq!(plural("person, people", 1.0), "person");
+        assert_eq!(plural("person, people", 3.0), "people");
+    }
+
+    #[test]
+    fn parse_splits_text_and_placeholders() {
+        let parts =
+            parse("Deploy {{ service }} took {{secs | duration:ms | default:a while}}.").unwrap();
+        assert_eq!(parts.len(), 5);
+        let Part::Var(placeholder) = &parts[3] else {
+            panic!("expected a placeholder");
+        };
+        assert_eq!(placeholder.name, "secs");
+        assert_eq!(
+            placeholder.helpers,
+            [("duration", Some("ms")), ("default", Some("a while"))]
+        );
+        assert_eq!(
+            variables("{{a}} {{b | number}} {{a | plural:x}}"),
+            ["a", "b"]
+        );
+        assert!(parse("no placeholders").is_ok());
+    }
+
+    #[test]
+    fn parse_rejects_bad_templates() {
+        for template in [
+            "{{name",
+            "{{ }}",
+            "{{name | shout}}",
+            "{{n | plural}}",
+            "{{n | default}}",
+            "{{n | duration:days}}",
+            "{{n | number:two}}",
+        ] {
+            assert!(parse(template).is_err(), "{} should be rejected", template);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Voice listing for the configured TTS provider
+
+use crate::TtsProvider;
+use axum::{
+    extract::{Json, Query},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tracing::warn;
+
+#[derive(Clone, Serialize)]
+pub struct VoiceInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VoiceList {
+    pub provider: String,
+    pub default_voice: String,
+    pub voices: Vec<VoiceInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct VoiceQuery {
+    /// Locale prefix filter, e.g. `en` or `en-GB`
+    pub locale: Option<String>,
+}
+
+const OPENAI_VOICES: [(&str, &str); 6] = [
+    ("alloy", "Neutral, balanced"),
+    ("echo", "Clear, authoritative male"),
+    ("fable", "Warm, expressive"),
+    ("onyx", "Deep, rich male"),
+    ("nova", "Friendly female"),
+    ("shimmer", "Bright, energetic female"),
+];
+
+/// Used when the Edge voice list can't be downloaded
+const FALLBACK_EDGE_VOICES: [(&str, &str, &str); 6] = [
+    ("en-US-AndrewNeural", "en-US", "Male"),
+    ("en-US-AriaNeural", "en-US", "Female"),
+    ("en-US-GuyNeural", "en-US", "Male"),
+    ("en-US-JennyNeural", "en-US", "Female"),
+    ("en-GB-RyanNeural", "en-GB", "Male"),
+    ("en-GB-SoniaNeural", "en-GB", "Female"),
+];
+
+/// Edge voices are fetched once from Microsoft and reused
+static EDGE_VOICES: Mutex<Option<Vec<VoiceInfo>>> = Mutex::new(None);
+
+async fn edge_voices() -> Vec<VoiceInfo> {
+    if let Some(voices) = EDGE_VOICES.lock().unwrap().clone() {
+        return voices;
+    }
+
+    match msedge_tts::voice::get_voices_list_async().await {
+        Ok(list) => {
+            let voices: Vec<VoiceInfo> = list
+                .into_iter()
+                .map(|v| VoiceInfo {
+                    name: v.short_name.unwrap_or(v.name),
+                    locale: v.locale,
+                    gender: v.gender,
+                    description: v.friendly_name,
+                })
+                .collect();
+            *EDGE_VOICES.lock().unwrap() = Some(voices.clone());
+            voices
+        }
+        Err(e) => {
+            warn!(
+                "Failed to fetch Edge TTS voice list, using built-in list: {}",
+                e
+            );
+            FALLBACK_EDGE_VOICES
+                .iter()
+                .map(|(name, locale, gender)| VoiceInfo {
+                    name: name.to_string(),
+                    locale: Some(locale.to_string()),
+                    gender: Some(gender.to_string()),
+                    description: None,
+                })
+                .collect()
+        }
+    }
+}
+
+/// Voices available from the configured provider, optionally filtered by locale prefix
+pub async fn list_voices(locale: Option<&str>) -> VoiceList {
+    let provider = TtsProvider::configured();
+
+    let (default_voice, voices) = match provider {
+        TtsProvider::Edge => (crate::resolve_edge_voice("alloy"), edge_voices().await),
+        TtsProvider::OpenAI => (
+            crate::default_voice(),
+            OPENAI_VOICES
+                .iter()
+                .map(|(name, description)| VoiceInfo {
+                    name: name.to_string(),
+                    locale: None,
+                    gender: None,
+                    description: Some(description.to_string()),
+                })
+                .collect(),
+        ),
+    };
+
+    let voices = match locale {
+        Some(prefix) => {
+            let prefix = prefix.to_lowercase();
+            voices
+                .into_iter()
+                .filter(|v| {
+                    v.locale
+                        .as_deref()
+                        .is_some_and(|l| l.to_lowercase().starts_with(&prefix))
+                })
+                .collect()
+        }
+        None => voices,
+    };
+
+    VoiceList {
+        provider: provider.as_str().to_string(),
+        default_voice,
+        voices,
+    }
+}
+
+/// GET /voices - voices for the configured TTS provider
+pub async fn voices_handler(Query(query): Query<VoiceQuery>) -> impl IntoResponse {
+    Json(list_voices(query.locale.as_deref()).await)
+}
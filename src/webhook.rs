@@ -0,0 +1,554 @@
+//! Named webhook routes that turn arbitrary JSON payloads into transmissions
+
+use crate::persisted::{Named, NamedStore};
+use crate::{AppState, PlayRequest};
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// A named webhook route. `text`, `profile`, `voice`, `tone`, `toast_urgency` and
+/// `instructions` are templates: `{{$.path}}` is replaced with the JSONPath value from the payload, and
+/// the message template helpers apply, e.g. `{{$.path | default:fallback}}` when the path matches
+/// nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSpec {
+    pub name: String,
+    pub text: String,
+    /// Named profile from the config; the route's own fields take precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toast_urgency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_toast: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_volume: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_ack: Option<bool>,
+    /// HMAC-SHA256 secret; when set, requests must carry a valid signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(default = "default_signature_header")]
+    pub signature_header: String,
+    /// Prefix before the hex digest in the signature header
+    #[serde(default = "default_signature_prefix")]
+    pub signature_prefix: String,
+}
+
+fn default_signature_header() -> String {
+    "X-Hub-Signature-256".to_string()
+}
+
+fn default_signature_prefix() -> String {
+    "sha256=".to_string()
+}
+
+impl WebhookSpec {
+    fn templates(&self) -> impl Iterator<Item = &str> {
+        [
+            Some(self.text.as_str()),
+            self.profile.as_deref(),
+            self.voice.as_deref(),
+            self.tone.as_deref(),
+            self.toast_urgency.as_deref(),
+            self.instructions.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Render the templates against a payload. Returns None if the text is empty.
+    fn render(&self, payload: &Value) -> Result<Option<PlayRequest>, String> {
+        let text = render_template(&self.text, payload)?;
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+
+        // Empty renders fall back to the usual defaults
+        let render_optional = |template: &Option<String>| -> Result<Option<String>, String> {
+            match template {
+                Some(template) => {
+                    let value = render_template(template, payload)?;
+                    Ok((!value.trim().is_empty()).then(|| value.trim().to_string()))
+                }
+                None => Ok(None),
+            }
+        };
+
+        Ok(Some(PlayRequest {
+            text: text.trim().to_string(),
+            profile: render_optional(&self.profile)?,
+            voice: render_optional(&self.voice)?,
+            instructions: render_optional(&self.instructions)?,
+            speed: self.speed,
+            volume: self.volume,
+            pitch: self.pitch,
+            voice_volume: self.voice_volume,
+            tone: render_optional(&self.tone)?,
+            enable_toast: self.enable_toast,
+            toast_urgency: render_optional(&self.toast_urgency)?,
+            require_ack: self.require_ack,
+            ..Default::default()
+        }))
+    }
+
+    /// Check the request signature against the shared secret, if one is configured
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), String> {
+        let Some(secret) = &self.secret else {
+            return Ok(());
+        };
+
+        let signature = headers
+            .get(self.signature_header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| format!("Missing {} header", self.signature_header))?;
+        let digest = signature
+            .trim()
+            .strip_prefix(self.signature_prefix.as_str())
+            .and_then(|hex_digest| hex::decode(hex_digest).ok())
+            .ok_or("Malformed signature")?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .map_err(|e| format!("Invalid webhook secret: {}", e))?;
+        mac.update(body);
+        mac.verify_slice(&digest)
+            .map_err(|_| "Signature mismatch".to_string())
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parse the JSONPath subset we support: `$`, `.key`, `['key']`, `[n]`, `[*]` and `.*`
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = || format!("Invalid JSONPath '{}'", path);
+    let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if rest.starts_with("..") {
+            return Err(format!("Recursive descent is not supported in '{}'", path));
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            if key.is_empty() {
+                return Err(invalid());
+            }
+            segments.push(match key {
+                "*" => Segment::Wildcard,
+                _ => Segment::Key(key.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let inner = after[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if let Ok(index) = inner.parse() {
+                Segment::Index(index)
+            } else if inner.len() >= 2
+                && (inner.starts_with('\'') && inner.ends_with('\'')
+                    || inner.starts_with('"') && inner.ends_with('"'))
+            {
+                Segment::Key(inner[1..inner.len() - 1].to_string())
+            } else {
+                return Err(invalid());
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Evaluate a JSONPath against a payload, returning every matching value
+fn select<'a>(root: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let mut current = vec![root];
+    for segment in parse_path(path)? {
+        current = current
+            .into_iter()
+            .flat_map(|value| -> Vec<&Value> {
+                match (&segment, value) {
+                    (Segment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                    (Segment::Index(index), Value::Array(items)) => {
+                        items.get(*index).into_iter().collect()
+                    }
+                    (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    Ok(current)
+}
+
+/// Fill each `{{$.path}}` in a template with payload values. Paths that match nothing
+/// render empty unless a `default` helper says otherwise.
+fn render_template(template: &str, payload: &Value) -> Result<String, String> {
+    let lookup = |path: &str| {
+        let mut values: Vec<Value> = select(payload, path)?
+            .into_iter()
+            .filter(|value| !value.is_null())
+            .cloned()
+            .collect();
+        Ok(match values.len() {
+            0 => None,
+            1 => values.pop(),
+            _ => Some(Value::Array(values)),
+        })
+    };
+    crate::templates::fill(template, lookup).map(|(text, _)| text)
+}
+
+/// Webhook routes shared between the management API and the ingestion handler
+pub type WebhookStore = NamedStore<WebhookSpec>;
+
+/// Check a route's name and templates
+fn validate(spec: &WebhookSpec) -> Result<(), String> {
+    if spec.name.trim().is_empty() {
+        return Err("Webhook name must not be empty".to_string());
+    }
+    // These names have dedicated handlers at the same path
+    if matches!(spec.name.as_str(), "alertmanager" | "slack" | "discord") {
+        return Err(format!("Webhook name '{}' is reserved", spec.name));
+    }
+    for template in spec.templates() {
+        render_template(template, &Value::Null)?;
+    }
+    Ok(())
+}
+
+impl Named for WebhookSpec {
+    type Spec = WebhookSpec;
+    const KIND: &'static str = "Webhook";
+    const FILE: &'static str = "webhooks file";
+
+    fn compile(spec: WebhookSpec) -> Result<Self, String> {
+        validate(&spec)?;
+        Ok(spec)
+    }
+
+    fn spec(&self) -> &WebhookSpec {
+        self
+    }
+
+    fn name(spec: &WebhookSpec) -> &str {
+        &spec.name
+    }
+}
+
+impl NamedStore<WebhookSpec> {
+    /// Routes with secrets redacted
+    fn list(&self) -> Vec<WebhookInfo> {
+        self.with_items(|webhooks| {
+            webhooks
+                .iter()
+                .map(|spec| WebhookInfo {
+                    signed: spec.secret.is_some(),
+                    spec: WebhookSpec {
+                        secret: None,
+                        ..spec.clone()
+                    },
+                })
+                .collect()
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookInfo {
+    #[serde(flatten)]
+    spec: WebhookSpec,
+    /// Whether requests must carry an HMAC signature
+    signed: bool,
+}
+
+/// POST /hooks/{name} - convert a JSON payload into a transmission using the named route
+pub async fn webhook_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> axum::response::Response {
+    let Some(spec) = state.webhooks.get(&name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Error: Webhook '{}' not found", name),
+        )
+            .into_response();
+    };
+
+    if let Err(e) = spec.verify(&headers, &body) {
+        warn!("Rejected webhook '{}' from {}: {}", name, addr.ip(), e);
+        return (StatusCode::UNAUTHORIZED, format!("Error: {}", e)).into_response();
+    }
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Error: Invalid JSON payload: {}", e),
+            )
+                .into_response();
+        }
+    };
+
+    let request = match spec.render(&payload) {
+        Ok(Some(request)) => request,
+        Ok(None) => {
+            info!("Webhook '{}' produced no text - ignoring", name);
+            return (
+                StatusCode::OK,
+                "Ignored: template produced no text".to_string(),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, format!("Error: {}", e)).into_response();
+        }
+    };
+
+    info!(
+        "Webhook '{}' received, adding to queue: {}",
+        name, request.text
+    );
+    let mut transmission = match request.into_transmission() {
+        Ok(transmission) => transmission,
+        Err(e) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, format!("Error: {}", e)).into_response();
+        }
+    };
+    transmission.client = Some(format!("webhook:{} ({})", name, addr.ip()));
+
+    crate::queued_response(&state, transmission)
+}
+
+/// GET /webhooks - list webhook routes (secrets redacted)
+pub async fn list_webhooks_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.webhooks.list())
+}
+
+/// Header proving a request knows a route's secret, to replace or remove it when
+/// `server.admin_token` isn't set
+const SECRET_HEADER: &str = "X-Webhook-Secret";
+
+/// Check that a request may create, replace or remove the route `name`. With
+/// `server.admin_token` set, the bearer token is required. Without it, anyone may manage
+/// routes, except that a route with a secret can only be changed by a request that
+/// sends that secret, so its signature check can't be bypassed by replacing it.
+fn authorize_management(
+    state: &AppState,
+    headers: &HeaderMap,
+    name: &str,
+) -> Result<(), (StatusCode, String)> {
+    if crate::config::get().server.admin_token.is_some() {
+        return crate::authorize_admin(headers);
+    }
+
+    let Some(secret) = state.webhooks.get(name).and_then(|spec| spec.secret) else {
+        return Ok(());
+    };
+    let provided = headers.get(SECRET_HEADER).and_then(|v| v.to_str().ok());
+    if provided.is_some_and(|provided| crate::alertmanager::secrets_match(provided, &secret)) {
+        return Ok(());
+    }
+    warn!(
+        "Refusing to change webhook '{}': its secret wasn't provided",
+        name
+    );
+    Err((
+        StatusCode::FORBIDDEN,
+        format!(
+            "Error: Webhook '{}' has a secret - send it in the {} header to replace or remove the route",
+            name, SECRET_HEADER
+        ),
+    ))
+}
+
+/// POST /webhooks - create or replace a webhook route
+pub async fn create_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(spec): Json<WebhookSpec>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize_management(&state, &headers, &spec.name) {
+        return rejection;
+    }
+    state.webhooks.create_response(spec)
+}
+
+/// DELETE /webhooks/{name} - remove a webhook route
+pub async fn delete_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize_management(&state, &headers, &name) {
+        return rejection;
+    }
+    state.webhooks.delete_response(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(route: Value) -> WebhookSpec {
+        serde_json::from_value(route).unwrap()
+    }
+
+    fn payload() -> Value {
+        json!({
+            "repository": { "name": "quindar", "owner key": "ops" },
+            "jobs": [
+                { "name": "lint", "ok": true },
+                { "name": "test", "ok": false },
+            ],
+            "count": 3,
+            "nothing": null,
+        })
+    }
+
+    #[test]
+    fn parse_path_accepts_the_supported_subset() {
+        for path in [
+            "$",
+            "$.a.b",
+            "$['key with spaces']",
+            "$[\"quoted\"]",
+            "$.items[0].name",
+            "$.items[*]",
+            "$.*",
+        ] {
+            assert!(parse_path(path).is_ok(), "{} should parse", path);
+        }
+        for path in ["", "a.b", "$..a", "$.", "$[", "$[abc]", "$.a[0"] {
+            assert!(parse_path(path).is_err(), "{} should be rejected", path);
+        }
+    }
+
+    #[test]
+    fn select_follows_nested_and_array_paths() {
+        let payload = payload();
+        let names = |path: &str| -> Vec<Value> {
+            select(&payload, path)
+                .unwrap()
+                .into_iter()
+                .cloned()
+                .collect()
+        };
+        assert_eq!(names("$.repository.name"), [json!("quindar")]);
+        assert_eq!(names("$.repository['owner key']"), [json!("ops")]);
+        assert_eq!(names("$.jobs[1].name"), [json!("test")]);
+        assert_eq!(names("$.jobs[*].name"), [json!("lint"), json!("test")]);
+        assert_eq!(names("$.jobs[5].name"), Vec::<Value>::new());
+        assert_eq!(names("$.missing.deeper"), Vec::<Value>::new());
+        assert!(select(&payload, "$..name").is_err());
+    }
+
+    #[test]
+    fn render_template_fills_paths() {
+        let payload = payload();
+        let render = |template: &str| render_template(template, &payload);
+        assert_eq!(
+            render("{{$.repository.name}}: {{$.jobs[*].name}}").unwrap(),
+            "quindar: lint and test"
+        );
+        assert_eq!(
+            render("{{$.count | plural:failure}}").unwrap(),
+            "3 failures"
+        );
+        assert_eq!(render("[{{$.missing}}{{$.nothing}}]").unwrap(), "[]");
+        assert_eq!(
+            render("{{$.missing | default:unknown}}").unwrap(),
+            "unknown"
+        );
+        assert!(render("{{$.repository.name").is_err());
+        assert!(render("{{repository.name}}").is_err());
+    }
+
+    #[test]
+    fn spec_render_builds_a_request() {
+        let route = spec(json!({
+            "name": "ci",
+            "text": " {{$.repository.name}} finished ",
+            "voice": "{{$.missing}}",
+            "tone": "{{$.jobs[0].ok | default:QUINDAR}}",
+            "volume": 2.0,
+        }));
+        let request = route.render(&payload()).unwrap().unwrap();
+        assert_eq!(request.text, "quindar finished");
+        assert_eq!(request.voice, None);
+        assert_eq!(request.tone.as_deref(), Some("true"));
+        assert_eq!(request.volume, Some(2.0));
+
+        let route = spec(json!({ "name": "ci", "text": "{{$.missing}}" }));
+        assert!(route.render(&payload()).unwrap().is_none());
+
+        let route = spec(json!({ "name": "ci", "text": "{{$.count | duration:days}}" }));
+        assert!(route.render(&payload()).is_err());
+    }
+
+    #[test]
+    fn verify_checks_the_signature() {
+        let route = spec(json!({ "name": "ci", "text": "x", "secret": "s3cret" }));
+        let body = br#"{"ok":true}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Hub-Signature-256", signature.parse().unwrap());
+        assert_eq!(route.verify(&headers, body), Ok(()));
+        assert_eq!(
+            route.verify(&headers, b"tampered"),
+            Err("Signature mismatch".to_string())
+        );
+
+        headers.insert("X-Hub-Signature-256", "sha256=zz".parse().unwrap());
+        assert_eq!(
+            route.verify(&headers, body),
+            Err("Malformed signature".to_string())
+        );
+
+        assert_eq!(
+            route.verify(&HeaderMap::new(), body),
+            Err("Missing X-Hub-Signature-256 header".to_string())
+        );
+
+        let unsigned = spec(json!({ "name": "ci", "text": "x" }));
+        assert_eq!(unsigned.verify(&HeaderMap::new(), body), Ok(()));
+    }
+}
@@ -0,0 +1,154 @@
+//! WebSocket API: submit transmissions, acknowledge, manage the queue and receive
+//! lifecycle events over one persistent connection
+
+use crate::{AppState, PlayRequest};
+use axum::{
+    extract::{
+        ConnectInfo, State, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::info;
+
+/// A client command. Any `ref` value is echoed back on the reply for correlation.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "ref", default)]
+    reference: Option<Value>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Command {
+    /// Queue a transmission - same fields as a POST /play body
+    Play {
+        #[serde(flatten)]
+        request: Box<PlayRequest>,
+    },
+    Ack {
+        id: String,
+    },
+    Cancel {
+        id: String,
+    },
+    Queue,
+    Ping,
+}
+
+/// GET /ws - upgrade to the WebSocket API
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Browsers let any page open a WebSocket, so only local pages may connect
+    if !crate::mcp::origin_allowed(&headers) {
+        return (StatusCode::FORBIDDEN, "Error: Origin not allowed").into_response();
+    }
+
+    let client = crate::client_name(&headers, &addr);
+    ws.on_upgrade(move |socket| connection(socket, state, client))
+        .into_response()
+}
+
+async fn connection(mut socket: WebSocket, state: Arc<AppState>, client: String) {
+    info!("WebSocket client connected: {}", client);
+    let mut events = state.events.subscribe();
+
+    loop {
+        let outgoing = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => handle(&state, &client, &text),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames aren't part of the protocol
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let mut message = serde_json::to_value(&event).unwrap_or_default();
+                    message["type"] = json!("event");
+                    message
+                }
+                Err(RecvError::Lagged(missed)) => json!({ "type": "lagged", "missed": missed }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if socket
+            .send(Message::Text(outgoing.to_string().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    info!("WebSocket client disconnected: {}", client);
+}
+
+/// Run one client command and build the reply
+fn handle(state: &AppState, client: &str, text: &str) -> Value {
+    let envelope: Envelope = match serde_json::from_str(text) {
+        Ok(envelope) => envelope,
+        Err(e) => return json!({ "type": "error", "message": format!("Invalid message: {}", e) }),
+    };
+
+    let mut reply = match envelope.command {
+        Command::Play { request } => {
+            info!(
+                "Received WebSocket request, adding to queue: {}",
+                request.text
+            );
+            let mut transmission = match request.into_transmission() {
+                Ok(transmission) => transmission,
+                Err(e) => return json!({ "type": "error", "message": e }),
+            };
+            transmission.client = Some(format!("ws:{}", client));
+            let ack_url = transmission
+                .ack
+                .as_ref()
+                .map(|ack| state.acks.ack_url(&ack.id));
+
+            match state.enqueue(transmission) {
+                Ok(id) => json!({ "type": "queued", "id": id, "ack_url": ack_url }),
+                Err(e) => json!({ "type": "error", "message": e }),
+            }
+        }
+        Command::Ack { id } => {
+            if state.acks.acknowledge(&id) {
+                info!(id = %id, "Transmission acknowledged");
+                json!({ "type": "acknowledged", "id": id })
+            } else {
+                json!({
+                    "type": "error",
+                    "message": format!("No pending acknowledgement for {}", id)
+                })
+            }
+        }
+        Command::Cancel { id } => match crate::queue::cancel_transmission(state, &id) {
+            Ok(()) => json!({ "type": "cancelled", "id": id }),
+            Err(e) => json!({ "type": "error", "message": e }),
+        },
+        Command::Queue => {
+            let mut status = serde_json::to_value(state.queue.status()).unwrap_or_default();
+            status["type"] = json!("queue");
+            status
+        }
+        Command::Ping => json!({ "type": "pong" }),
+    };
+
+    if let Some(reference) = envelope.reference {
+        reply["ref"] = reference;
+    }
+    reply
+}